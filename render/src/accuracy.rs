@@ -0,0 +1,244 @@
+/// Checks how accurate the archived forecasts were by comparing them to the observations archived
+/// around the time they were for, at a few different lead times.
+
+use chrono::prelude::*;
+use chrono::Duration;
+use image::{Rgb, RgbImage};
+use imageproc::drawing::draw_filled_rect_mut;
+use imageproc::rect::Rect;
+
+use crate::{ArchiveRecord, CurrentWeather, FontChain};
+use crate::draw::{HAlign, TextBox, TextStyle, draw_text};
+
+/// How far ahead of the time it was fetched to check the forecast
+const LEAD_TIMES_HOURS: [i64; 3] = [1, 24, 72];
+/// How far an observation can be from the forecast time and still be used to check it
+const MAX_OBSERVATION_OFFSET_MINUTES: i64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccuracyVariable {
+    /// Forecast minus observed temperature in °F
+    Temperature,
+    /// Forecast rain probability minus whether any precipitation was observed, both from 0 to 1.
+    /// The rms error squared is the Brier score.
+    Precipitation,
+}
+
+impl AccuracyVariable {
+    pub fn name(&self) -> &str {
+        match self {
+            AccuracyVariable::Temperature => "temp_f",
+            AccuracyVariable::Precipitation => "precip_prob",
+        }
+    }
+}
+
+/// How far off the forecast was for one variable at one lead time
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccuracyStats {
+    pub lead_hours: i64,
+    pub variable: AccuracyVariable,
+    /// Number of forecasts that had an observation to compare to. The errors are all zero if
+    /// this is zero.
+    pub count: usize,
+    /// Average of forecast minus observed, so positive means the forecast ran warm or wet
+    pub bias: f64,
+    pub mean_abs_error: f64,
+    pub rms_error: f64,
+}
+
+impl AccuracyStats {
+    fn from_errors(lead_hours: i64, variable: AccuracyVariable, errors: &[f64]) -> AccuracyStats {
+        let count = errors.len();
+        let mean = |values: &mut dyn Iterator<Item = f64>| values.sum::<f64>() / count.max(1) as f64;
+
+        AccuracyStats {
+            lead_hours,
+            variable,
+            count,
+            bias: mean(&mut errors.iter().copied()),
+            mean_abs_error: mean(&mut errors.iter().map(|e| e.abs())),
+            rms_error: mean(&mut errors.iter().map(|e| e * e)).sqrt(),
+        }
+    }
+}
+
+/// Compares every archived forecast to the archived observation closest to the time it was for,
+/// returning the temperature and precipitation stats for each lead time. Each record's current
+/// weather is treated as an observation at the time it was fetched.
+pub fn compute_accuracy(records: &[ArchiveRecord]) -> Vec<AccuracyStats> {
+    let mut observations: Vec<(DateTime<FixedOffset>, &CurrentWeather)> = records.iter()
+        .map(|r| (r.fetched, &r.current_weather))
+        .collect();
+    observations.sort_by_key(|(time, _)| *time);
+
+    let mut stats = Vec::new();
+    for lead_hours in LEAD_TIMES_HOURS {
+        let mut temp_errors = Vec::new();
+        let mut precip_errors = Vec::new();
+
+        for record in records {
+            let target = record.fetched + Duration::hours(lead_hours);
+            // the hourly forecast point whose hour has the target in it
+            let point = record.forecast.iter()
+                .find(|p| p.time <= target && target < p.time + Duration::hours(1));
+            let observed = nearest_observation(&observations, target);
+            let (point, observed) = match (point, observed) {
+                (Some(point), Some(observed)) => (point, observed),
+                _ => continue,
+            };
+
+            temp_errors.push((point.temp_f - observed.temp_f) as f64);
+            // stations often leave the precipitation out, which doesn't mean it was dry
+            if let Some(rain_in) = observed.rain_in {
+                let occurred = if rain_in > 0.0 { 1.0 } else { 0.0 };
+                precip_errors.push(point.rain_prob as f64 / 100.0 - occurred);
+            }
+        }
+
+        stats.push(AccuracyStats::from_errors(lead_hours, AccuracyVariable::Temperature, &temp_errors));
+        stats.push(AccuracyStats::from_errors(lead_hours, AccuracyVariable::Precipitation, &precip_errors));
+    }
+
+    stats
+}
+
+/// Finds the observation closest to `target` in the sorted `observations`, if there is one close
+/// enough
+fn nearest_observation<'a>(observations: &[(DateTime<FixedOffset>, &'a CurrentWeather)],
+        target: DateTime<FixedOffset>) -> Option<&'a CurrentWeather> {
+    let idx = observations.partition_point(|(time, _)| *time < target);
+    let before = idx.checked_sub(1).and_then(|i| observations.get(i));
+    let after = observations.get(idx);
+
+    [before, after].into_iter().flatten()
+        .map(|(time, observed)| ((*time - target).num_minutes().abs(), *observed))
+        .filter(|(offset, _)| *offset <= MAX_OBSERVATION_OFFSET_MINUTES)
+        .min_by_key(|(offset, _)| *offset)
+        .map(|(_, observed)| observed)
+}
+
+pub fn accuracy_csv(stats: &[AccuracyStats]) -> String {
+    let mut csv = String::from("lead_hours,variable,count,bias,mean_abs_error,rms_error\n");
+    for s in stats {
+        csv.push_str(&format!("{},{},{},{:.3},{:.3},{:.3}\n",
+            s.lead_hours, s.variable.name(), s.count, s.bias, s.mean_abs_error, s.rms_error));
+    }
+    csv
+}
+
+/// Draws a table of the stats with a bar for the temperature bias at each lead time, in red if the
+/// forecast ran warm and black if it ran cold
+pub fn draw_accuracy_report(stats: &[AccuracyStats], records: &[ArchiveRecord], font: &FontChain) -> RgbImage {
+    let mut image = RgbImage::from_fn(800, 480, |_, _| -> Rgb<u8> { Rgb([255u8, 255u8, 255u8]) });
+    let black = image::Rgb([0u8, 0u8, 0u8]);
+    let red = image::Rgb([255u8, 0u8, 0u8]);
+
+    let title_style = TextStyle::new(font, 36.0, black);
+    let text_style = TextStyle::new(font, 20.0, black);
+    draw_text(&mut image, "Forecast accuracy", TextBox::new(10.0, 10.0, 790.0, 480.0), &title_style);
+    let date_range = match (records.first(), records.last()) {
+        (Some(first), Some(last)) => format!("{} runs from {} to {}", records.len(),
+            first.fetched.format("%-m/%-d"), last.fetched.format("%-m/%-d")),
+        _ => "no archived runs".to_string(),
+    };
+    draw_text(&mut image, &date_range, TextBox::new(10.0, 55.0, 790.0, 480.0), &text_style);
+
+    let columns = [("lead", 10.0), ("temp bias", 110.0), ("temp error", 250.0), ("rain bias", 400.0), ("rain brier", 540.0)];
+    let header_y = 100.0;
+    for (name, x) in columns {
+        draw_text(&mut image, name, TextBox::new(x, header_y, x + 140.0, 480.0), &text_style);
+    }
+
+    let find = |lead_hours: i64, variable: AccuracyVariable| {
+        stats.iter().find(|s| s.lead_hours == lead_hours && s.variable == variable)
+    };
+    // the biggest bias sets the scale of the bars, with at least 5°F on either side
+    let bar_scale = stats.iter()
+        .filter(|s| s.variable == AccuracyVariable::Temperature)
+        .map(|s| s.bias.abs())
+        .fold(5.0, f64::max);
+    let bar_center = 400.0;
+    let bar_half_width = 380.0;
+
+    for (i, lead_hours) in LEAD_TIMES_HOURS.iter().enumerate() {
+        let y = header_y + 35.0 * (i + 1) as f32;
+        let temp = find(*lead_hours, AccuracyVariable::Temperature).filter(|s| s.count > 0);
+        let precip = find(*lead_hours, AccuracyVariable::Precipitation).filter(|s| s.count > 0);
+        let cells = [
+            format!("{lead_hours}h"),
+            temp.map(|s| format!("{:+.1}°", s.bias)).unwrap_or_else(|| "--".into()),
+            temp.map(|s| format!("{:.1}°", s.mean_abs_error)).unwrap_or_else(|| "--".into()),
+            precip.map(|s| format!("{:+.0}%", s.bias * 100.0)).unwrap_or_else(|| "--".into()),
+            precip.map(|s| format!("{:.2}", s.rms_error * s.rms_error)).unwrap_or_else(|| "--".into()),
+        ];
+        for ((_, x), cell) in columns.iter().zip(cells) {
+            draw_text(&mut image, &cell, TextBox::new(*x, y, x + 140.0, 480.0), &text_style);
+        }
+
+        if let Some(temp) = temp {
+            let bar_y = 270 + 60 * i as i32;
+            let bar_width = (temp.bias.abs() / bar_scale * bar_half_width).round() as i32;
+            let color = if temp.bias > 0.0 { red } else { black };
+            let bar_x = if temp.bias > 0.0 { bar_center as i32 } else { bar_center as i32 - bar_width };
+            if bar_width > 0 {
+                draw_filled_rect_mut(&mut image, Rect::at(bar_x, bar_y).of_size(bar_width as u32, 30), color);
+            }
+            let label_style = TextStyle { h_align: HAlign::Right, ..text_style };
+            draw_text(&mut image, &format!("{lead_hours}h"), TextBox::new(0.0, bar_y as f32 + 4.0, 60.0, 480.0), &label_style);
+        }
+    }
+
+    // the zero line for the bars
+    let bars_bottom = 270 + 60 * LEAD_TIMES_HOURS.len() as i32 - 20;
+    draw_filled_rect_mut(&mut image, Rect::at(bar_center as i32, 260).of_size(1, (bars_bottom - 260) as u32), black);
+    let scale_text = format!("temp bias, -{bar_scale:.0}° to +{bar_scale:.0}°");
+    draw_text(&mut image, &scale_text, TextBox::new(10.0, 450.0, 790.0, 480.0),
+        &TextStyle { h_align: HAlign::Center, ..text_style });
+
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ForecastPoint;
+
+    #[test]
+    fn test_compute_accuracy() {
+        let start = DateTime::parse_from_rfc3339("2023-10-12T00:00:00-04:00").unwrap();
+        // hourly runs for 4 days where it's always 50° and dry, and the forecast is always 2°
+        // warmer with a 20% chance of rain. Every other observation has no precipitation.
+        let records: Vec<ArchiveRecord> = (0..96).map(|i| {
+            let fetched = start + Duration::hours(i) + Duration::minutes(5);
+            let forecast = (0..96)
+                .map(|h| ForecastPoint::new(start + Duration::hours(i + h), 52, 20))
+                .collect();
+            ArchiveRecord {
+                fetched,
+                current_weather: CurrentWeather::new("Clear".into(), 50, (i % 2 == 0).then_some(0.0)),
+                forecast,
+            }
+        }).collect();
+
+        let stats = compute_accuracy(&records);
+        assert_eq!(stats.len(), 6);
+        for s in &stats {
+            // the last `lead_hours` runs don't have an observation to compare to yet
+            match s.variable {
+                AccuracyVariable::Temperature => {
+                    assert_eq!(s.count, 96 - s.lead_hours as usize);
+                    assert_eq!(s.bias, 2.0);
+                    assert_eq!(s.mean_abs_error, 2.0);
+                }
+                AccuracyVariable::Precipitation => {
+                    assert_eq!(s.count, (96 - s.lead_hours as usize) / 2);
+                    assert!((s.bias - 0.2).abs() < 1e-9);
+                }
+            }
+        }
+
+        let csv = accuracy_csv(&stats);
+        assert_eq!(csv.lines().nth(1), Some("1,temp_f,95,2.000,2.000,2.000"));
+    }
+}
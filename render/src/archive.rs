@@ -0,0 +1,259 @@
+/// Keeps a record of the observation and forecast from every run, so that they can be compared
+/// later. Each day gets its own append-only file of json lines in the archive directory, and days
+/// older than the retention period are deleted.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+use chrono::Duration;
+use serde_json::{json, Value};
+
+use crate::{CurrentWeather, ForecastPoint, FullForecast};
+
+const ARCHIVE_EXTENSION: &str = "jsonl";
+
+#[derive(Debug, Clone)]
+pub struct ArchiveOptions {
+    /// Directory to keep the archive files in
+    pub dir: PathBuf,
+    /// Number of days of files to keep, including today
+    pub retention_days: u32,
+}
+
+impl ArchiveOptions {
+    /// Reads the `archive` section of the env data. Returns None if there isn't one.
+    pub fn from_json(data: &Value) -> Option<ArchiveOptions> {
+        let dir = data["dir"].as_str()?.into();
+        let retention_days = data["retention_days"].as_u64().unwrap_or(30) as u32;
+        if retention_days == 0 {
+            panic!("archive retention_days must be at least 1");
+        }
+
+        Some(ArchiveOptions {
+            dir,
+            retention_days,
+        })
+    }
+
+    fn file_path(&self, date: NaiveDate) -> PathBuf {
+        self.dir.join(date.format("%Y-%m-%d").to_string()).with_extension(ARCHIVE_EXTENSION)
+    }
+}
+
+/// What was fetched on a single run
+#[derive(Debug, Clone)]
+pub struct ArchiveRecord {
+    pub fetched: DateTime<FixedOffset>,
+    pub current_weather: CurrentWeather,
+    pub forecast: FullForecast,
+}
+
+impl ArchiveRecord {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "fetched": self.fetched.to_rfc3339(),
+            "current_weather": {
+                "description": self.current_weather.description,
+                "temp_f": self.current_weather.temp_f,
+                "rain_in": self.current_weather.rain_in,
+                "wind_speed": self.current_weather.wind_speed,
+                "wind_gust": self.current_weather.wind_gust,
+                "wind_direction": self.current_weather.wind_direction,
+                "humidity": self.current_weather.humidity,
+                "dewpoint_f": self.current_weather.dewpoint_f,
+                "pressure_inhg": self.current_weather.pressure_inhg,
+                "pressure_tendency_inhg": self.current_weather.pressure_tendency_inhg,
+                "wind_chill_f": self.current_weather.wind_chill_f,
+                "heat_index_f": self.current_weather.heat_index_f,
+            },
+            "forecast": self.forecast.iter().map(point_to_json).collect::<Vec<Value>>(),
+        })
+    }
+
+    pub fn from_json(data: &Value) -> Option<ArchiveRecord> {
+        let current_weather = &data["current_weather"];
+        Some(ArchiveRecord {
+            fetched: DateTime::parse_from_rfc3339(data["fetched"].as_str()?).ok()?,
+            current_weather: CurrentWeather {
+                description: current_weather["description"].as_str()?.into(),
+                temp_f: current_weather["temp_f"].as_i64()? as i32,
+                rain_in: current_weather["rain_in"].as_f64().map(|r| r as f32),
+                // these weren't always recorded, so older records don't have them
+                wind_speed: current_weather["wind_speed"].as_u64(),
+                wind_gust: current_weather["wind_gust"].as_u64(),
+                wind_direction: current_weather["wind_direction"].as_f64().map(|d| d as f32),
+                humidity: current_weather["humidity"].as_u64(),
+                dewpoint_f: current_weather["dewpoint_f"].as_i64().map(|d| d as i32),
+                pressure_inhg: current_weather["pressure_inhg"].as_f64().map(|p| p as f32),
+                pressure_tendency_inhg: current_weather["pressure_tendency_inhg"].as_f64().map(|p| p as f32),
+                wind_chill_f: current_weather["wind_chill_f"].as_i64().map(|w| w as i32),
+                heat_index_f: current_weather["heat_index_f"].as_i64().map(|h| h as i32),
+            },
+            forecast: data["forecast"].as_array()?.iter()
+                .map(point_from_json)
+                .collect::<Option<FullForecast>>()?,
+        })
+    }
+}
+
+fn point_from_json(data: &Value) -> Option<ForecastPoint> {
+    Some(ForecastPoint {
+        time: DateTime::parse_from_rfc3339(data["time"].as_str()?).ok()?,
+        temp_f: data["temp_f"].as_i64()? as i32,
+        rain_prob: data["rain_prob"].as_u64()?,
+        precip_in: data["precip_in"].as_f64().map(|p| p as f32),
+        snowfall_in: data["snowfall_in"].as_f64().map(|s| s as f32),
+        wind_speed: data["wind_speed"].as_u64(),
+        wind_direction: data["wind_direction"].as_f64().map(|d| d as f32),
+        sky_cover: data["sky_cover"].as_u64(),
+        dewpoint_f: data["dewpoint_f"].as_i64().map(|d| d as i32),
+        apparent_temp_f: data["apparent_temp_f"].as_i64().map(|t| t as i32),
+        short_desc: data["short_desc"].as_str().map(String::from),
+    })
+}
+
+fn point_to_json(point: &ForecastPoint) -> Value {
+    json!({
+        "time": point.time.to_rfc3339(),
+        "temp_f": point.temp_f,
+        "rain_prob": point.rain_prob,
+        "precip_in": point.precip_in,
+        "snowfall_in": point.snowfall_in,
+        "wind_speed": point.wind_speed,
+        "wind_direction": point.wind_direction,
+        "sky_cover": point.sky_cover,
+        "dewpoint_f": point.dewpoint_f,
+        "apparent_temp_f": point.apparent_temp_f,
+        "short_desc": point.short_desc,
+    })
+}
+
+/// Deletes archive files from before the retention period. Files in the directory that don't
+/// look like archive files are left alone.
+///
+/// This has to be called before the file system is locked down.
+pub fn prune_archive(options: &ArchiveOptions, today: NaiveDate) {
+    let oldest = today - Duration::days(options.retention_days as i64 - 1);
+    let entries = match std::fs::read_dir(&options.dir) {
+        Ok(entries) => entries,
+        // nothing to prune if the archive hasn't been created yet
+        Err(_) => return,
+    };
+
+    for path in entries.filter_map(|entry| entry.ok().map(|e| e.path())) {
+        if let Some(date) = archive_file_date(&path) {
+            if date < oldest {
+                std::fs::remove_file(&path)
+                    .unwrap_or_else(|e| panic!("failed to remove old archive file {path:?}: {e}"));
+            }
+        }
+    }
+}
+
+fn archive_file_date(path: &Path) -> Option<NaiveDate> {
+    if path.extension()? != ARCHIVE_EXTENSION {
+        return None;
+    }
+    NaiveDate::parse_from_str(path.file_stem()?.to_str()?, "%Y-%m-%d").ok()
+}
+
+/// Reads every record in the archive, oldest first. Lines that can't be parsed, like one that was
+/// only partly written, are skipped with a warning.
+///
+/// This has to be called before the file system is locked down.
+pub fn read_archive(options: &ArchiveOptions) -> Vec<ArchiveRecord> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&options.dir)
+        .expect("failed to read archive directory")
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| archive_file_date(path).is_some())
+        .collect();
+    paths.sort();
+
+    let mut records = Vec::new();
+    for path in paths {
+        let contents = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("failed to read archive file {path:?}: {e}"));
+        for (i, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let record = serde_json::from_str(line).ok()
+                .and_then(|data| ArchiveRecord::from_json(&data));
+            match record {
+                Some(record) => records.push(record),
+                None => eprintln!("skipping invalid archive record on line {} of {path:?}", i + 1),
+            }
+        }
+    }
+    records.sort_by_key(|r| r.fetched);
+
+    records
+}
+
+/// Opens today's archive file for appending, creating the directory and file if needed.
+///
+/// This has to be called before the file system is locked down. Afterwards only writing to the
+/// returned file is allowed.
+pub fn open_archive_file(options: &ArchiveOptions, today: NaiveDate) -> File {
+    std::fs::create_dir_all(&options.dir).expect("failed to create archive directory");
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(options.file_path(today))
+        .expect("failed to open archive file")
+}
+
+/// Appends the record to the archive file as a single line
+pub fn append_archive_record(file: &mut File, record: &ArchiveRecord) {
+    let mut line = record.to_json().to_string();
+    line.push('\n');
+    file.write_all(line.as_bytes()).expect("failed to write archive record");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive() {
+        let dir = std::env::temp_dir().join(format!("halldisplay_archive_test_{}", std::process::id()));
+        let options = ArchiveOptions { dir: dir.clone(), retention_days: 2 };
+        let today = NaiveDate::from_ymd_opt(2023, 10, 16).unwrap();
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("2023-10-14.jsonl"), "").unwrap();
+        std::fs::write(dir.join("2023-10-15.jsonl"), "").unwrap();
+        std::fs::write(dir.join("notes.txt"), "").unwrap();
+        prune_archive(&options, today);
+        assert!(!dir.join("2023-10-14.jsonl").exists());
+        assert!(dir.join("2023-10-15.jsonl").exists());
+        assert!(dir.join("notes.txt").exists());
+
+        let record = ArchiveRecord {
+            fetched: DateTime::parse_from_rfc3339("2023-10-16T20:30:00-04:00").unwrap(),
+            current_weather: CurrentWeather {
+                humidity: Some(60),
+                wind_chill_f: Some(50),
+                ..CurrentWeather::new("Clear".into(), 55, Some(0.0))
+            },
+            forecast: crate::test_data::test_data1(),
+        };
+        for _ in 0..2 {
+            let mut file = open_archive_file(&options, today);
+            append_archive_record(&mut file, &record);
+        }
+        // a partly written line is skipped
+        std::fs::write(dir.join("2023-10-15.jsonl"), "{\"fetched\": \"2023-10-15T").unwrap();
+
+        let records = read_archive(&options);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].fetched, record.fetched);
+        assert_eq!(records[0].current_weather.temp_f, 55);
+        assert_eq!(records[0].current_weather.rain_in, Some(0.0));
+        assert_eq!(records[0].current_weather.humidity, Some(60));
+        assert_eq!(records[0].current_weather.wind_chill_f, Some(50));
+        assert_eq!(records[0].current_weather.heat_index_f, None);
+        assert_eq!(records[0].forecast, record.forecast);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,349 @@
+/// Parsing for BDF and PCF bitmap fonts. Bitmap fonts are drawn pixel-exact, which looks much
+/// cleaner on the e-ink display at small sizes than thresholded TrueType glyphs.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BitmapGlyph {
+    /// Distance to move the caret after drawing this glyph
+    pub advance: i32,
+    /// Distance from the origin to the left edge of the bitmap
+    pub left: i32,
+    /// Distance from the baseline up to the top row of the bitmap
+    pub top: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Row-major, `true` where the pixel is set
+    pub pixels: Vec<bool>,
+}
+
+impl BitmapGlyph {
+    pub fn pixel(&self, x: u32, y: u32) -> bool {
+        self.pixels[(y*self.width + x) as usize]
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BitmapFont {
+    /// Pixels above the baseline
+    pub ascent: i32,
+    /// Pixels below the baseline, as a positive number
+    pub descent: i32,
+    glyphs: HashMap<char, BitmapGlyph>,
+}
+
+impl BitmapFont {
+    pub fn glyph(&self, c: char) -> Option<&BitmapGlyph> {
+        self.glyphs.get(&c)
+    }
+
+    /// Bitmap fonts only have one size, so they are scaled up by whole pixels to get as close to
+    /// the requested font size as possible.
+    pub fn pixel_scale(&self, font_size: f32) -> i32 {
+        let height = (self.ascent + self.descent).max(1) as f32;
+        ((font_size / height).round() as i32).max(1)
+    }
+}
+
+/// Parses a BDF font. Glyph encodings are assumed to be unicode code points.
+pub fn parse_bdf(data: &str) -> BitmapFont {
+    let mut ascent = None;
+    let mut descent = None;
+    let mut bounding_box = None;
+    let mut glyphs = HashMap::new();
+
+    let mut lines = data.lines();
+    while let Some(line) = lines.next() {
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("FONT_ASCENT") => ascent = fields.next().and_then(|v| v.parse().ok()),
+            Some("FONT_DESCENT") => descent = fields.next().and_then(|v| v.parse().ok()),
+            Some("FONTBOUNDINGBOX") => bounding_box = Some(parse_bdf_bbx(fields)),
+            Some("STARTCHAR") => {
+                if let Some((c, glyph)) = parse_bdf_char(&mut lines) {
+                    glyphs.insert(c, glyph);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // FONT_ASCENT and FONT_DESCENT are optional properties, fall back to the bounding box
+    let (_, bbx_height, _, bbx_y) = bounding_box.expect("bdf font did not have a FONTBOUNDINGBOX");
+    BitmapFont {
+        ascent: ascent.unwrap_or(bbx_height as i32 + bbx_y),
+        descent: descent.unwrap_or(-bbx_y),
+        glyphs,
+    }
+}
+
+/// Parses the `BBX`/`FONTBOUNDINGBOX` fields into (width, height, x offset, y offset)
+fn parse_bdf_bbx<'a>(mut fields: impl Iterator<Item = &'a str>) -> (u32, u32, i32, i32) {
+    let mut next = || fields.next().and_then(|v| v.parse::<i32>().ok())
+        .expect("failed to parse bdf bounding box");
+    (next() as u32, next() as u32, next(), next())
+}
+
+/// Parses everything between STARTCHAR and ENDCHAR. Returns None for unencoded glyphs.
+fn parse_bdf_char<'a>(lines: &mut impl Iterator<Item = &'a str>) -> Option<(char, BitmapGlyph)> {
+    let mut encoding = None;
+    let mut advance = 0;
+    let mut bbx = (0, 0, 0, 0);
+    let mut rows = Vec::new();
+    let mut in_bitmap = false;
+
+    for line in lines.by_ref() {
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("ENDCHAR") => break,
+            Some("ENCODING") => {
+                encoding = fields.next().and_then(|v| v.parse::<i64>().ok())
+                    .filter(|e| *e >= 0)
+                    .and_then(|e| char::from_u32(e as u32));
+            }
+            Some("DWIDTH") => {
+                advance = fields.next().and_then(|v| v.parse().ok())
+                    .expect("failed to parse bdf DWIDTH");
+            }
+            Some("BBX") => bbx = parse_bdf_bbx(fields),
+            Some("BITMAP") => in_bitmap = true,
+            Some(row) if in_bitmap => rows.push(row.to_string()),
+            _ => {}
+        }
+    }
+
+    let (width, height, x_offset, y_offset) = bbx;
+    let mut pixels = vec![false; (width*height) as usize];
+    for (y, row) in rows.iter().take(height as usize).enumerate() {
+        let bytes: Vec<u8> = (0..row.len()/2)
+            .map(|i| u8::from_str_radix(&row[2*i..2*i+2], 16).expect("invalid bdf bitmap row"))
+            .collect();
+        for x in 0..width as usize {
+            pixels[y*width as usize + x] = bytes.get(x/8).is_some_and(|b| b & (0x80 >> (x%8)) != 0);
+        }
+    }
+
+    let glyph = BitmapGlyph {
+        advance,
+        left: x_offset,
+        top: y_offset + height as i32,
+        width,
+        height,
+        pixels,
+    };
+
+    encoding.map(|c| (c, glyph))
+}
+
+const PCF_MAGIC: &[u8] = b"\x01fcp";
+
+const PCF_ACCELERATORS: u32 = 1 << 1;
+const PCF_METRICS: u32 = 1 << 2;
+const PCF_BITMAPS: u32 = 1 << 3;
+const PCF_BDF_ENCODINGS: u32 = 1 << 5;
+const PCF_BDF_ACCELERATORS: u32 = 1 << 8;
+
+const PCF_GLYPH_PAD_MASK: u32 = 3;
+const PCF_BYTE_MASK: u32 = 1 << 2;
+const PCF_BIT_MASK: u32 = 1 << 3;
+const PCF_SCAN_UNIT_MASK: u32 = 3 << 4;
+const PCF_COMPRESSED_METRICS: u32 = 0x100;
+
+/// Reads a single table out of a pcf file, using the byte order from the table's format field.
+struct PcfTable<'a> {
+    data: &'a [u8],
+    format: u32,
+    pos: usize,
+}
+
+impl<'a> PcfTable<'a> {
+    fn new(data: &'a [u8]) -> PcfTable<'a> {
+        // the format field itself is always little endian
+        let format = u32::from_le_bytes(data[..4].try_into().unwrap());
+        PcfTable { data, format, pos: 4 }
+    }
+
+    fn big_endian(&self) -> bool {
+        self.format & PCF_BYTE_MASK != 0
+    }
+
+    fn bytes<const N: usize>(&mut self) -> [u8; N] {
+        let bytes = self.data[self.pos..self.pos+N].try_into()
+            .expect("pcf table was truncated");
+        self.pos += N;
+        bytes
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.bytes::<1>()[0]
+    }
+
+    fn i16(&mut self) -> i16 {
+        let b = self.bytes();
+        if self.big_endian() { i16::from_be_bytes(b) } else { i16::from_le_bytes(b) }
+    }
+
+    fn i32(&mut self) -> i32 {
+        let b = self.bytes();
+        if self.big_endian() { i32::from_be_bytes(b) } else { i32::from_le_bytes(b) }
+    }
+}
+
+/// (left side bearing, right side bearing, character width, ascent, descent)
+type PcfMetrics = (i32, i32, i32, i32, i32);
+
+/// Parses an uncompressed PCF font, as produced by `bdftopcf`.
+pub fn parse_pcf(data: &[u8]) -> BitmapFont {
+    if data.len() < 8 || &data[..4] != PCF_MAGIC {
+        panic!("pcf font did not start with the pcf header (is it gzipped?)");
+    }
+
+    let mut tables = HashMap::new();
+    let table_count = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+    for i in 0..table_count {
+        let entry = &data[8 + 16*i..8 + 16*(i+1)];
+        let field = |n: usize| u32::from_le_bytes(entry[4*n..4*n+4].try_into().unwrap()) as usize;
+        let (table_type, size, offset) = (field(0) as u32, field(2), field(3));
+        tables.insert(table_type, &data[offset..offset+size]);
+    }
+    let table = |table_type: u32| tables.get(&table_type)
+        .map(|data| PcfTable::new(data));
+
+    let mut accelerators = table(PCF_BDF_ACCELERATORS).or_else(|| table(PCF_ACCELERATORS))
+        .expect("pcf font did not have an accelerators table");
+    // skip the flags to get to the font ascent/descent
+    accelerators.pos += 8;
+    let ascent = accelerators.i32();
+    let descent = accelerators.i32();
+
+    let mut metrics_table = table(PCF_METRICS).expect("pcf font did not have a metrics table");
+    let metrics: Vec<PcfMetrics> = if metrics_table.format & PCF_COMPRESSED_METRICS != 0 {
+        let count = metrics_table.i16() as u16;
+        (0..count).map(|_| {
+            let mut next = || metrics_table.u8() as i32 - 0x80;
+            (next(), next(), next(), next(), next())
+        }).collect()
+    }
+    else {
+        let count = metrics_table.i32();
+        (0..count).map(|_| {
+            let metric = (metrics_table.i16() as i32, metrics_table.i16() as i32, metrics_table.i16() as i32,
+                metrics_table.i16() as i32, metrics_table.i16() as i32);
+            // skip attributes
+            metrics_table.i16();
+            metric
+        }).collect()
+    };
+
+    let mut bitmap_table = table(PCF_BITMAPS).expect("pcf font did not have a bitmaps table");
+    let format = bitmap_table.format;
+    let glyph_count = bitmap_table.i32() as usize;
+    let offsets: Vec<usize> = (0..glyph_count).map(|_| bitmap_table.i32() as usize).collect();
+    // bitmap sizes for each of the 4 possible paddings, we only need the one the file uses
+    let sizes: Vec<usize> = (0..4).map(|_| bitmap_table.i32() as usize).collect();
+    let bitmap_start = bitmap_table.pos;
+    let bitmap_data = normalize_pcf_bitmap(
+        &bitmap_table.data[bitmap_start..bitmap_start + sizes[(format & PCF_GLYPH_PAD_MASK) as usize]],
+        format);
+    let glyph_pad = 1 << (format & PCF_GLYPH_PAD_MASK);
+
+    let mut encodings = table(PCF_BDF_ENCODINGS).expect("pcf font did not have an encodings table");
+    let min_byte2 = encodings.i16() as u32;
+    let max_byte2 = encodings.i16() as u32;
+    let min_byte1 = encodings.i16() as u32;
+    let max_byte1 = encodings.i16() as u32;
+    let _default_char = encodings.i16();
+
+    let mut glyphs = HashMap::new();
+    for byte1 in min_byte1..=max_byte1 {
+        for byte2 in min_byte2..=max_byte2 {
+            let glyph_index = encodings.i16() as u16;
+            if glyph_index == 0xffff || glyph_index as usize >= glyph_count {
+                continue;
+            }
+            let c = match char::from_u32((byte1 << 8) | byte2) {
+                Some(c) => c,
+                None => continue,
+            };
+
+            let (lsb, rsb, advance, glyph_ascent, glyph_descent) = metrics[glyph_index as usize];
+            let width = (rsb - lsb).max(0) as u32;
+            let height = (glyph_ascent + glyph_descent).max(0) as u32;
+            let row_bytes = (width as usize).div_ceil(8).div_ceil(glyph_pad) * glyph_pad;
+            let offset = offsets[glyph_index as usize];
+
+            let mut pixels = vec![false; (width*height) as usize];
+            for y in 0..height as usize {
+                for x in 0..width as usize {
+                    let byte = bitmap_data[offset + y*row_bytes + x/8];
+                    pixels[y*width as usize + x] = byte & (0x80 >> (x%8)) != 0;
+                }
+            }
+
+            glyphs.insert(c, BitmapGlyph {
+                advance,
+                left: lsb,
+                top: glyph_ascent,
+                width,
+                height,
+                pixels,
+            });
+        }
+    }
+
+    BitmapFont {
+        ascent,
+        descent,
+        glyphs,
+    }
+}
+
+/// Converts pcf bitmap data into most significant bit first, with the bytes in order.
+fn normalize_pcf_bitmap(data: &[u8], format: u32) -> Vec<u8> {
+    let mut data = data.to_vec();
+    if format & PCF_BIT_MASK == 0 {
+        for b in &mut data {
+            *b = b.reverse_bits();
+        }
+    }
+
+    // when the byte order and bit order don't match, the bytes in each scan unit are swapped
+    let scan_unit = 1 << ((format & PCF_SCAN_UNIT_MASK) >> 4);
+    let msb_bytes = format & PCF_BYTE_MASK != 0;
+    let msb_bits = format & PCF_BIT_MASK != 0;
+    if msb_bytes != msb_bits && scan_unit > 1 {
+        for unit in data.chunks_mut(scan_unit) {
+            unit.reverse();
+        }
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bitmap_fonts() {
+        let bdf = parse_bdf(include_str!("../tests/bitmap_test.bdf"));
+        let pcf = parse_pcf(include_bytes!("../tests/bitmap_test.pcf"));
+
+        assert_eq!((bdf.ascent, bdf.descent), (7, 2));
+        assert_eq!((pcf.ascent, pcf.descent), (7, 2));
+        assert_eq!(bdf.pixel_scale(24.0), 3);
+
+        let a = bdf.glyph('A').unwrap();
+        assert_eq!((a.advance, a.left, a.top, a.width, a.height), (6, 0, 7, 5, 7));
+        let rows: Vec<String> = (0..a.height)
+            .map(|y| (0..a.width).map(|x| if a.pixel(x, y) { '#' } else { '.' }).collect())
+            .collect();
+        assert_eq!(rows, [".###.", "#...#", "#...#", "#####", "#...#", "#...#", "#...#"]);
+
+        for c in ['A', 'g', 'é'] {
+            assert_eq!(bdf.glyph(c), pcf.glyph(c), "glyph {c} did not match");
+        }
+        assert!(bdf.glyph('B').is_none());
+        assert!(pcf.glyph('B').is_none());
+    }
+}
@@ -0,0 +1,400 @@
+/// A chart of values over time, with any number of series that each have their own style and y
+/// axis, and decorations for where the days start. The forecast graph is built on top of this.
+
+use chrono::prelude::*;
+use chrono::Duration;
+use image::{Rgb, RgbImage};
+use imageproc::drawing::{Canvas, draw_filled_rect_mut, draw_hollow_rect_mut, draw_line_segment_mut, BresenhamLineIter};
+use imageproc::rect::Rect;
+
+use crate::{Fill, TextBox};
+
+/// Maps times onto the x axis of a chart. Points are placed by their time rather than their
+/// index, so gaps in the data show up as gaps on the chart.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeAxis {
+    pub start: DateTime<FixedOffset>,
+    pub end: DateTime<FixedOffset>,
+    step_secs: f32,
+    /// Pixels between points that are `step` apart
+    pub horiz_spacing: f32,
+}
+
+impl TimeAxis {
+    /// An axis going from `start` at the left edge to `end` at the right edge of a chart `width`
+    /// pixels wide
+    pub fn new(start: DateTime<FixedOffset>, end: DateTime<FixedOffset>, step: Duration, width: f32) -> TimeAxis {
+        let step_secs = step.num_seconds() as f32;
+        let steps = ((end - start).num_seconds() as f32 / step_secs).max(1.0);
+
+        TimeAxis {
+            start,
+            end,
+            step_secs,
+            horiz_spacing: (width-1.0) / steps,
+        }
+    }
+
+    pub fn x(&self, t: DateTime<FixedOffset>) -> f32 {
+        self.horiz_spacing * ((t - self.start).num_seconds() as f32 / self.step_secs)
+    }
+
+    /// The time at the x coordinate `x`, the inverse of `TimeAxis::x`
+    pub fn time_at(&self, x: f32) -> DateTime<FixedOffset> {
+        self.start + Duration::seconds((x / self.horiz_spacing * self.step_secs) as i64)
+    }
+
+    /// Returns None if `t` is outside of the chart
+    pub fn time_to_x(&self, t: DateTime<FixedOffset>) -> Option<f32> {
+        (self.start <= t && t <= self.end).then(|| self.x(t))
+    }
+}
+
+/// Maps values onto the y axis of a chart, with `min` at the bottom edge and `max` at the top
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValueAxis {
+    pub min: f32,
+    pub max: f32,
+    /// Round the distance from the bottom down to a whole number of pixels
+    pub whole_pixels: bool,
+}
+
+impl ValueAxis {
+    pub fn new(min: f32, max: f32) -> ValueAxis {
+        ValueAxis {
+            min,
+            max,
+            whole_pixels: false,
+        }
+    }
+
+    /// The y coordinate of `value` on a chart `height` pixels tall. y points down, so it's
+    /// subtracted from the height.
+    pub fn y(&self, value: f32, height: f32) -> f32 {
+        let fraction = (value - self.min) / (self.max - self.min);
+        let offset = if self.whole_pixels { (fraction*(height-1.0)).floor() } else { fraction*(height-1.0) };
+        height - offset - 1.0
+    }
+}
+
+/// Draw `on` pixels out of every `period` along the line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dash {
+    pub on: u32,
+    pub period: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineStyle {
+    /// Thickness in pixels, centered on the line
+    pub width: u32,
+    /// Solid if None
+    pub dash: Option<Dash>,
+}
+
+impl LineStyle {
+    pub fn solid(width: u32) -> LineStyle {
+        LineStyle { width, dash: None }
+    }
+
+    pub fn dashed(width: u32, on: u32, period: u32) -> LineStyle {
+        LineStyle { width, dash: Some(Dash { on, period }) }
+    }
+
+    /// Offsets from the line for each pixel of its thickness
+    fn offsets(&self) -> std::ops::RangeInclusive<i32> {
+        let width = self.width.max(1) as i32;
+        -(width - 1) / 2..=width / 2
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SeriesStyle {
+    /// A line through the points
+    Line(LineStyle),
+    /// A line through the points with the area under it shaded. The fill for each point is used
+    /// up to the next point, and the last fill is used for any points after the end of `fills`.
+    Area(LineStyle, Vec<Fill>),
+    /// An outlined bar from the bottom of the chart up to each point, taking up `fraction` of the
+    /// space between points and shaded with `fill`
+    Bars { fraction: f32, fill: Fill },
+}
+
+/// A set of values over time drawn on a chart
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    /// Points in time order
+    pub points: Vec<(DateTime<FixedOffset>, f32)>,
+    pub axis: ValueAxis,
+    pub color: Rgb<u8>,
+    pub style: SeriesStyle,
+}
+
+/// Draws a label for a day in the given box
+pub type DayLabel<'a> = &'a dyn Fn(&mut RgbImage, NaiveDate, TextBox);
+
+/// Draws something extra onto the chart
+pub type Overlay<'a> = &'a dyn Fn(&mut RgbImage);
+
+pub struct Chart<'a> {
+    pub axis: TimeAxis,
+    /// Drawn in order, except that areas and bars are all drawn before any lines so that they
+    /// don't cover them up
+    pub series: Vec<Series>,
+    /// Draw a vertical line where each day starts. Days are split at the first point of the day
+    /// in the first series, and at midnight before the first series starts.
+    pub day_separators: bool,
+    /// Called for each day with a separator, other than the last day, with the space between its
+    /// separator and the next day's
+    pub day_label: Option<DayLabel<'a>>,
+    /// Tick marks along the bottom every this many hours, with longer ones at noon
+    pub hour_ticks: Option<u32>,
+    /// Dashed vertical lines at these times
+    pub markers: Vec<(DateTime<FixedOffset>, Rgb<u8>)>,
+    /// Drawn over the areas and bars but under the ticks, markers and lines, e.g. for a legend
+    pub overlay: Option<Overlay<'a>>,
+}
+
+impl<'a> Chart<'a> {
+    /// A chart with no series or decorations
+    pub fn new(axis: TimeAxis) -> Chart<'a> {
+        Chart {
+            axis,
+            series: Vec::new(),
+            day_separators: false,
+            day_label: None,
+            hour_ticks: None,
+            markers: Vec::new(),
+            overlay: None,
+        }
+    }
+
+    /// Draws the chart over the whole of `image`, on top of whatever is already there
+    pub fn draw(&self, image: &mut RgbImage) {
+        if self.day_separators {
+            self.draw_day_separators(image);
+        }
+
+        let is_line = |s: &&Series| matches!(s.style, SeriesStyle::Line(_));
+        for series in self.series.iter().filter(|s| !is_line(s)) {
+            self.draw_series(image, series);
+        }
+        if let Some(overlay) = self.overlay {
+            overlay(image);
+        }
+
+        if let Some(every_hours) = self.hour_ticks {
+            self.draw_hour_ticks(image, every_hours);
+        }
+        for (time, color) in &self.markers {
+            self.draw_marker(image, *time, *color);
+        }
+
+        for series in self.series.iter().filter(is_line) {
+            self.draw_series(image, series);
+        }
+    }
+
+    fn draw_day_separators(&self, image: &mut RgbImage) {
+        let black = image::Rgb([0u8, 0u8, 0u8]);
+        let (width, height) = (image.width() as f32, image.height() as f32);
+        let times: Vec<DateTime<FixedOffset>> = match self.series.first() {
+            Some(series) => series.points.iter().map(|(t, _)| *t).collect(),
+            None => Vec::new(),
+        };
+        let last_date = times.last().map(|t| t.date_naive());
+
+        for w in times.windows(2) {
+            let date = w[1].date_naive();
+            if w[0].date_naive() == date {
+                continue;
+            }
+            let x = self.axis.x(w[1]);
+            draw_line_segment_mut(image, (x, 0.0), (x, height), black);
+            if let Some(day_label) = self.day_label.filter(|_| Some(date) != last_date) {
+                let next_day_x = self.axis.x(w[1] + Duration::days(1)).min(width);
+                day_label(image, date, TextBox::new(x + 5.0, 0.0, next_day_x - 5.0, height));
+            }
+        }
+
+        // the points only start partway through, so the separators before them go at midnight
+        let first_point = times.first().copied().unwrap_or(self.axis.end);
+        let start = self.axis.start;
+        let midnights = start.date_naive().iter_days()
+            .map(|d| start.timezone().from_local_datetime(&d.and_hms_opt(0, 0, 0).unwrap()).earliest())
+            .map_while(|t| t.filter(|t| *t < first_point));
+        for midnight in midnights.filter(|t| *t > start) {
+            let x = self.axis.x(midnight);
+            draw_line_segment_mut(image, (x, 0.0), (x, height), black);
+        }
+    }
+
+    fn draw_hour_ticks(&self, image: &mut RgbImage, every_hours: u32) {
+        let black = image::Rgb([0u8, 0u8, 0u8]);
+        let height = image.height() as f32;
+        let first = self.axis.start.with_minute(0).unwrap().with_second(0).unwrap();
+        let first = first + Duration::hours(((every_hours - first.hour() % every_hours) % every_hours) as i64);
+        let ticks = std::iter::successors(Some(first), |t| Some(*t + Duration::hours(every_hours as i64)));
+        for t in ticks.take_while(|t| *t <= self.axis.end) {
+            if let Some(x) = self.axis.time_to_x(t) {
+                // midnight already has the day separator, so make the noon ticks a bit longer
+                let tick_height = if t.hour() == 12 { 12.0 } else { 6.0 };
+                draw_line_segment_mut(image, (x, height - tick_height), (x, height), black);
+            }
+        }
+    }
+
+    fn draw_marker(&self, image: &mut RgbImage, time: DateTime<FixedOffset>, color: Rgb<u8>) {
+        let (width, height) = (image.width() as f32, image.height() as f32);
+        if let Some(x) = self.axis.time_to_x(time) {
+            for y in (0..height as u32).step_by(8) {
+                for dy in 0..4 {
+                    for dx in [0.0, 1.0] {
+                        image.draw_pixel((x + dx).min(width - 1.0) as u32, (y + dy).min(height as u32 - 1), color);
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_series(&self, image: &mut RgbImage, series: &Series) {
+        let height = image.height() as f32;
+        let points: Vec<(f32, f32)> = series.points.iter()
+            .map(|(t, value)| (self.axis.x(*t), series.axis.y(*value, height)))
+            .collect();
+
+        match &series.style {
+            SeriesStyle::Line(line) => draw_line(image, &points, line, series.color),
+            SeriesStyle::Area(line, fills) => {
+                draw_line(image, &points, line, series.color);
+                fill_under(image, &points, fills);
+            }
+            SeriesStyle::Bars { fraction, fill } => {
+                let bar_width = (self.axis.horiz_spacing * fraction).round().max(2.0);
+                draw_bars(image, &points, bar_width, *fill, series.color);
+            }
+        }
+    }
+}
+
+fn draw_line(image: &mut RgbImage, points: &[(f32, f32)], line: &LineStyle, color: Rgb<u8>) {
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    match line.dash {
+        None => {
+            for w in points.windows(2) {
+                let (p1, p2) = (w[0], w[1]);
+                for offset in line.offsets() {
+                    let offset = offset as f32;
+                    draw_line_segment_mut(image, (p1.0, p1.1 + offset), (p2.0, p2.1 + offset), color);
+                }
+            }
+        }
+        Some(dash) => {
+            // the dashes carry on from one segment to the next
+            let mut count = 0;
+            for w in points.windows(2) {
+                for (x, y) in BresenhamLineIter::new(w[0], w[1]) {
+                    count += 1;
+                    if count % dash.period >= dash.on {
+                        continue;
+                    }
+                    let x = x.max(0).min(width - 1) as u32;
+                    for offset in line.offsets() {
+                        let y = (y + offset).max(0).min(height - 1) as u32;
+                        image.draw_pixel(x, y, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Shades under the line through `points`, down to the bottom of the image
+fn fill_under(image: &mut RgbImage, points: &[(f32, f32)], fills: &[Fill]) {
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    let last_fill = match fills.last() {
+        Some(fill) => *fill,
+        None => return,
+    };
+
+    // iterate over the pixels of the line directly to keep track of the lowest point of the line
+    // in each column, and which fill goes under it
+    let mut columns: Vec<Option<(u32, Fill)>> = vec![None; width as usize];
+    for (i, w) in points.windows(2).enumerate() {
+        let fill = fills.get(i).copied().unwrap_or(last_fill);
+        for (x, y) in BresenhamLineIter::new(w[0], w[1]) {
+            let x = x.max(0).min(width - 1) as usize;
+            let y = y.max(0).min(height - 1) as u32;
+            let max_y = columns[x].map(|(max_y, _)| max_y.max(y)).unwrap_or(y);
+            columns[x] = Some((max_y, fill));
+        }
+    }
+
+    for (x, column) in columns.iter().enumerate() {
+        if let Some((max_y, fill)) = column {
+            fill.fill_rect(image, x as u32, max_y + 1, x as u32, height as u32 - 1);
+        }
+    }
+}
+
+fn draw_bars(image: &mut RgbImage, points: &[(f32, f32)], bar_width: f32, fill: Fill, color: Rgb<u8>) {
+    let white = image::Rgb([255u8, 255u8, 255u8]);
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    for (x, y) in points {
+        let top = y.round().max(0.0);
+        // nothing to draw for a zero value
+        if top >= height - 1.0 {
+            continue;
+        }
+
+        let left = (x - bar_width / 2.0).round().max(0.0) as u32;
+        let right = ((x + bar_width / 2.0).round() as u32).min(width as u32 - 1);
+        let top = top as u32;
+        // a white background so the bars stand out from anything under them
+        let bar = Rect::at(left as i32, top as i32).of_size(right - left + 1, height as u32 - top);
+        draw_filled_rect_mut(image, bar, white);
+        fill.fill_rect(image, left, top, right, height as u32 - 1);
+        draw_hollow_rect_mut(image, bar, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pattern;
+
+    #[test]
+    fn test_chart() {
+        let black = Rgb([0u8, 0u8, 0u8]);
+        let white = Rgb([255u8, 255u8, 255u8]);
+        let start = DateTime::parse_from_rfc3339("2023-10-16T18:00:00-04:00").unwrap();
+        let end = start + Duration::hours(12);
+        let axis = TimeAxis::new(start, end, Duration::hours(1), 13.0);
+        assert_eq!(axis.x(start + Duration::hours(3)), 3.0);
+        assert_eq!(axis.time_at(3.0), start + Duration::hours(3));
+        assert_eq!(axis.time_to_x(end + Duration::hours(1)), None);
+
+        // a flat line halfway up with solid shading under it
+        let solid = Fill { density: crate::Density::Dense, ..Fill::new(Pattern::Vertical, black) };
+        let points = (0..=12).map(|h| (start + Duration::hours(h), 50.0)).collect();
+        let mut chart = Chart::new(axis);
+        chart.day_separators = true;
+        chart.series.push(Series {
+            points,
+            axis: ValueAxis { whole_pixels: true, ..ValueAxis::new(0.0, 100.0) },
+            color: black,
+            style: SeriesStyle::Area(LineStyle::solid(1), vec![solid]),
+        });
+
+        let mut image = RgbImage::from_pixel(13, 11, white);
+        chart.draw(&mut image);
+        // the line is at y 5, with the shading under it
+        assert_eq!(*image.get_pixel(1, 5), black);
+        assert_eq!(*image.get_pixel(1, 4), white);
+        assert_eq!(*image.get_pixel(4, 8), black);
+        assert_eq!(*image.get_pixel(5, 8), white);
+        // midnight is 6 hours in
+        assert_eq!(*image.get_pixel(6, 0), black);
+        assert_eq!(*image.get_pixel(7, 0), white);
+    }
+}
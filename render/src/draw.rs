@@ -1,18 +1,93 @@
 /// Handles drawing the actual pixels onto a canvas
 /// See the `render` module for where these are called from
 
-use crate::Forecast5Day;
+use crate::{Chart, Density, Fill, FontChain, ForecastPeriod, ForecastPoint, ForecastWindow, LineStyle, Location, MoonPhase,
+    Observation, Pattern, PrecipType, PressureTrend, Series, SeriesStyle, TimeAxis, ValueAxis, WeatherIcon,
+    draw_weather_icon, format_daily_temp, format_precip_amount, is_night};
 
 use chrono::prelude::*;
+use chrono::Duration;
+use serde_json::Value;
 
+use crate::text;
 use crate::text::draw_text_mut;
-use imageproc::drawing::{Canvas, draw_line_segment_mut, BresenhamLineIter};
+use imageproc::drawing::{Canvas, draw_filled_rect_mut, draw_line_segment_mut};
+use imageproc::rect::Rect;
 use image::{RgbImage, Rgb};
-use rusttype::{point, Font, Scale};
+use rusttype::Scale;
+
+
+/// How to show the wind forecast
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum WindDisplay {
+    #[default]
+    None,
+    /// Wind speed as a dashed line on the graph with its own scale
+    Series,
+    /// A strip of arrows under the graph pointing the direction the wind is blowing
+    Arrows,
+}
+
+/// Optional decorations for the forecast graph, all off by default
+#[derive(Debug, Clone, Default)]
+pub struct GraphOptions {
+    /// Tick marks along the bottom of the graph every 6 hours
+    pub hour_ticks: bool,
+    /// A dashed vertical line at the current time
+    pub now_marker: bool,
+    /// Dotted horizontal lines at round temperature values
+    pub temp_gridlines: bool,
+    /// Labels for the rain probability scale next to the graph
+    pub rain_labels: bool,
+    pub wind: WindDisplay,
+    /// Extend the graph a day into the past and draw the observed temperatures there
+    pub history: bool,
+    /// Bars for the forecast precipitation amounts with their own scale, and each day's total
+    /// under its label
+    pub precip_amounts: bool,
+}
+
+impl GraphOptions {
+    /// Reads the `graph` section of the env data
+    pub fn from_json(data: &Value) -> GraphOptions {
+        let flag = |name: &str| data[name].as_bool().unwrap_or(false);
+        GraphOptions {
+            hour_ticks: flag("hour_ticks"),
+            now_marker: flag("now_marker"),
+            temp_gridlines: flag("temp_gridlines"),
+            rain_labels: flag("rain_labels"),
+            history: flag("history"),
+            precip_amounts: flag("precip_amounts"),
+            wind: match data["wind"].as_str() {
+                None => WindDisplay::None,
+                Some("series") => WindDisplay::Series,
+                Some("arrows") => WindDisplay::Arrows,
+                Some(wind) => panic!("unknown graph wind display {wind}, expected series or arrows"),
+            },
+        }
+    }
+}
+
+/// Pressure changes over 3 hours of at least this much are drawn in red
+const PRESSURE_RAPID_CHANGE_INHG: f32 = 0.06;
 
+/// Scales for the precipitation bars in inches. The smallest one that fits the biggest bar is used.
+const PRECIP_SCALES_IN: [f32; 6] = [0.1, 0.25, 0.5, 1.0, 2.0, 5.0];
+
+/// Everything besides the forecast itself that the forecast graph depends on
+pub struct GraphContext<'a> {
+    /// Used for the now marker and to cut off the observations
+    pub now: DateTime<FixedOffset>,
+    /// Used to shade the night, if it's set
+    pub location: Option<&'a Location>,
+    /// Observed temperatures, drawn if the history is shown
+    pub observations: &'a [Observation],
+}
 
-pub fn draw_5day_graph(forecast: &Forecast5Day,
-        width: i64, height: i64, font: &Font) -> RgbImage {
+pub fn draw_5day_graph(forecast: &ForecastWindow,
+        width: i64, height: i64, font: &FontChain,
+        context: &GraphContext, options: &GraphOptions) -> RgbImage {
+    let now = context.now;
     let mut image = RgbImage::from_fn(width as u32, height as u32, |_, _| -> Rgb<u8> { Rgb([255u8, 255u8, 255u8]) });
     let black = image::Rgb([0u8, 0u8, 0u8]);
     let red = image::Rgb([255u8, 0u8, 0u8]);
@@ -21,173 +96,813 @@ pub fn draw_5day_graph(forecast: &Forecast5Day,
     let width = width as f32;
 
     let forecast_data = forecast.filtered_forecast();
-    let horiz_spacing = (width-1.0) / (forecast_data.len()-1) as f32;
-
+    let axis = graph_time_axis(&forecast_data, forecast.filtered_step(), width, options);
 
     let daily_minmax = forecast.daily_minmax_temps();
-    let (_min_temp, _max_temp) = forecast.week_minmax_temps();
-    // scale the temp values so that the temperature graph doesn't go to right to the border
-    let min_temp_scale = 0.9 * _min_temp as f32;
-    let max_temp_scale = 1.1 * _max_temp as f32;
-    // convert rain probabilities 0-100 into pixel heights
-    // convert temp into pixel heights based on max and min temps
-    // y axis points down so we subtract from height
-
-    // compute the pixel y coordinate for each graph's data points based on the total height of the graph region
-    // the points are computed by scaling each point relative to the overall height, and for the
-    // temperature graph, the min and max temperature. the rain precipitation is a % so we just
-    // scale from 0 to 100. there's a bit of tricky off by one stuff as well.
-    let temp_x = (0..forecast_data.len()).map(|x| horiz_spacing * x as f32);
-    let temp_y = forecast_data.iter().map(|(_d, temp, _r)| {
-        height - ((*temp as f32 - min_temp_scale)/ (max_temp_scale - min_temp_scale))*(height-1.0).floor() - 1.0
-    });
-
-    let rain_x = (0..forecast_data.len()).map(|x| horiz_spacing * x as f32);
-    let rain_y = forecast_data.iter().map(|(_d, _t, rain_p)| {
-        height - ((*rain_p as f32 / 100.0)*(height-1.0)).floor() - 1.0
-    });
+    let daily_precip = if options.precip_amounts { forecast.daily_precip_totals() } else { Default::default() };
+    let observations = if options.history { context.observations } else { &[] };
+    let temp_axis = graph_temp_axis(forecast, observations);
+    // the temperature is scaled relative to its axis range, and the rain probability is a % so it's
+    // scaled from 0 to 100. the rain is snapped to whole pixels so that 0% is right on the bottom.
+    let temp_scale = ValueAxis::new(temp_axis.min, temp_axis.max);
+    let rain_scale = ValueAxis { whole_pixels: true, ..ValueAxis::new(0.0, 100.0) };
+    let precip_types: Vec<Option<PrecipType>> = forecast_data.iter().map(|p| p.precip_type()).collect();
+
+    if let Some(location) = context.location {
+        // a light dot pattern over the night, drawn first so everything else goes on top of it
+        let night_tops: Vec<Option<u32>> = (0..width as u32)
+            .map(|x| is_night(&axis.time_at(x as f32), location).then_some(0))
+            .collect();
+        Fill { density: Density::Sparse, ..Fill::new(Pattern::Dots, black) }
+            .fill_under_curve(&mut image, &night_tops);
+    }
 
-    let dates = forecast_data.iter().map(|(d, _t, _r)| d.clone());
-
-    let temp_points: Vec<(f32, f32)> = temp_x.zip(temp_y).collect();
-    let rain_points: Vec<(DateTime<_>, (f32, f32))> =
-        dates.zip(rain_x.zip(rain_y)).collect();
-
-    let lastday = rain_points.iter().last().unwrap().0.day();
-    // draw vertical lines with legends to split into days of the week
-    for w in rain_points.windows(2) {
-        let d1 = w[0].0;
-        let d2 = w[1].0;
-        if d1.day() != d2.day() {
-            let x = w[1].1.0;
-            let day = d2.day();
-            let day_letter = &d2.weekday().to_string()[..1];
-            draw_line_segment_mut(&mut image, (x, 0.0), (x, height), black);
-            if day != lastday {
-                let (day_min, day_max) = daily_minmax.get(&day).expect(&format!("daily minmax not found for day {day}"));
-                let text = format!("{day_letter} {day_max} {day_min}");
-                draw_text_left(&mut image, &text, x + 5.0, 0.0, &font, 36.0);
+    if options.temp_gridlines {
+        let step = temp_axis.step as i32;
+        // the axis bounds are on the edges of the graph, so only draw the lines between them
+        let first = temp_axis.min as i32 + step;
+        for temp in (first..temp_axis.max as i32).step_by(step as usize) {
+            let y = temp_scale.y(temp as f32, height);
+            for x in (0..width as u32).step_by(4) {
+                image.draw_pixel(x, y as u32, black);
             }
+            let bbox = TextBox::new(2.0, 0.0, width, y - 1.0);
+            draw_text(&mut image, &temp.to_string(), bbox,
+                &TextStyle { v_align: VAlign::Bottom, ..TextStyle::new(font, 16.0, black) });
         }
     }
 
-    // draw the actual graph, iterating over the pixels directly so that we can keep track of the
-    // heights of the graph for shading in under the graph later.
-    let mut max_y = vec![None; width as usize];
-    for w in rain_points.windows(2) {
-        let p1 = w[0].1;
-        let p2 = w[1].1;
-        for (x, y) in BresenhamLineIter::new(p1, p2) {
-            let x = x.max(0).min((width - 1.0) as i32) as usize;
-            let y = y.max(0).min((height - 1.0) as i32) as u32;
-
-            if max_y[x].is_none() || max_y[x].filter(|ym| y > *ym).is_some() {
-                max_y[x] = Some(y);
+    let day_label = |image: &mut RgbImage, date: NaiveDate, bbox: TextBox| {
+        let temps = daily_minmax.get(&date).copied().unwrap_or_default();
+        let day_letter = &date.weekday().to_string()[..1];
+        let text = format!("{day_letter} {} {}", format_daily_temp(temps.high), format_daily_temp(temps.low));
+        // shrink the label if it would run into the next day, e.g. with negative temps
+        let label = draw_text(image, &text, bbox,
+            &TextStyle { overflow: Overflow::ShrinkToFit { min_size: 20.0 }, ..TextStyle::new(font, 36.0, black) });
+
+        // dry days are left blank to keep the graph clear
+        if let Some(total) = daily_precip.get(&date).filter(|t| t.precip_in >= 0.01) {
+            let mut text = format_precip_amount(total.precip_in);
+            if total.snowfall_in >= 0.1 {
+                text += &format!(" {:.1}\" snow", total.snowfall_in);
             }
-            image.draw_pixel(x as u32, y, Rgb([0u8,0u8,0u8]));
+            let bbox = TextBox { top: label.bottom, ..bbox };
+            draw_text(image, &text, bbox,
+                &TextStyle { overflow: Overflow::ShrinkToFit { min_size: 12.0 }, ..TextStyle::new(font, 20.0, black) });
         }
+    };
+
+    // only rain is ever shown without a legend, since that's what the shading means by default.
+    // points where the kind is unknown are shaded like rain, so they count as rain here.
+    let shaded_types: Vec<PrecipType> = precip_types.iter()
+        .take(precip_types.len().saturating_sub(1))
+        .map(|t| t.unwrap_or(PrecipType::Rain))
+        .collect();
+    let shown_types: Vec<PrecipType> = [PrecipType::Rain, PrecipType::Snow, PrecipType::Mixed].into_iter()
+        .filter(|t| shaded_types.contains(t))
+        .collect();
+    let legend = |image: &mut RgbImage| draw_precip_legend(image, &shown_types, font);
+
+    let mut chart = Chart::new(axis);
+    chart.day_separators = true;
+    chart.day_label = Some(&day_label);
+    chart.hour_ticks = options.hour_ticks.then_some(6);
+    if options.now_marker {
+        chart.markers.push((now, red));
+    }
+    // the legend goes over the shading, but under the lines so it doesn't cut them off
+    if shown_types.iter().any(|t| *t != PrecipType::Rain) {
+        chart.overlay = Some(&legend);
     }
 
-    // using some math that took me a bit to get correct, draw a nice little shading pattern under
-    // the graph, using the above max_y to determine the limits of the pattern
-    for (x, y, p) in image.enumerate_pixels_mut() {
-        let ym = y%6;
-        let yd = y/6;
-        let xm = (x+2*yd)%6;
-        let xcond = (xm == ym) || (xm == ym+1);
-        let ycond = ym < 3;
+    // the rain goes first since the days are split up by its points
+    chart.series.push(Series {
+        points: forecast_data.iter().map(|p| (p.time, p.rain_prob as f32)).collect(),
+        axis: rain_scale,
+        color: black,
+        style: SeriesStyle::Area(LineStyle::solid(1), precip_types.iter().map(|t| precip_fill(*t)).collect()),
+    });
 
-        let g_cond = max_y[x as usize].filter(|ym| y > *ym).is_some();
+    let precip_scale = if options.precip_amounts { precip_bar_scale(&forecast_data) } else { None };
+    if let Some(precip_scale) = precip_scale {
+        // the tallest bar only goes two thirds of the way up so the bars stay clear of the day labels
+        chart.series.push(Series {
+            points: forecast_data.iter().map(|p| (p.time, p.precip_in.unwrap_or(0.0))).collect(),
+            axis: ValueAxis::new(0.0, precip_scale * 1.5),
+            color: black,
+            // leave a gap between the bars so they can be told apart, and stripe them so they
+            // stand out from the rain hatching
+            style: SeriesStyle::Bars {
+                fraction: 0.6,
+                fill: Fill { density: Density::Dense, ..Fill::new(Pattern::Vertical, black) },
+            },
+        });
+    }
 
-        if xcond && ycond && g_cond {
-            *p = Rgb([0u8,0u8,0u8]);
-        }
+    // scale from 0 to at least 20mph so that calm days don't look windy
+    let max_wind = forecast_data.iter().filter_map(|p| p.wind_speed).max().unwrap_or(0);
+    let wind_scale = (max_wind.div_ceil(10) * 10).max(20) as f32;
+    if options.wind == WindDisplay::Series {
+        // dashed so that it can be told apart from the rain graph
+        chart.series.push(Series {
+            points: forecast_data.iter().map(|p| (p.time, p.wind_speed.unwrap_or(0) as f32)).collect(),
+            axis: ValueAxis::new(0.0, wind_scale),
+            color: black,
+            style: SeriesStyle::Line(LineStyle::dashed(2, 5, 8)),
+        });
+    }
+
+    if options.history {
+        // dashed so that it can be told apart from the forecast
+        chart.series.push(Series {
+            points: observations.iter()
+                .filter(|o| axis.start <= o.time && o.time <= now)
+                .map(|o| (o.time, o.temp_f as f32))
+                .collect(),
+            axis: temp_scale,
+            color: red,
+            style: SeriesStyle::Line(LineStyle::dashed(3, 6, 10)),
+        });
     }
 
-    // Draw the actual temperature graph last so it goes on top of everything
-    for w in temp_points.windows(2) {
-        let p1 = w[0];
-        let p2 = w[1];
-        draw_line_segment_mut(&mut image, (p1.0, p1.1 + 1.0), (p2.0, p2.1 + 1.0), red);
-        draw_line_segment_mut(&mut image, p1, p2, red);
-        draw_line_segment_mut(&mut image, (p1.0, p1.1 - 1.0), (p2.0, p2.1 - 1.0), red);
+    // the actual temperature graph goes last so it goes on top of everything
+    chart.series.push(Series {
+        points: forecast_data.iter().map(|p| (p.time, p.temp_f as f32)).collect(),
+        axis: temp_scale,
+        color: red,
+        style: SeriesStyle::Line(LineStyle::solid(3)),
+    });
+
+    chart.draw(&mut image);
+
+    let scale_style = TextStyle { h_align: HAlign::Right, v_align: VAlign::Bottom, ..TextStyle::new(font, 16.0, black) };
+    if options.wind == WindDisplay::Series {
+        let bbox = TextBox::new(0.0, height - 20.0, width - 2.0, height);
+        draw_text(&mut image, &format!("wind 0-{wind_scale} mph"), bbox, &scale_style);
+    }
+    if let Some(precip_scale) = precip_scale {
+        // the wind scale goes in the same corner, so go above it
+        let bottom = if options.wind == WindDisplay::Series { height - 20.0 } else { height };
+        let step_hours = forecast_data.windows(2)
+            .map(|w| (w[1].time - w[0].time).num_hours())
+            .min()
+            .unwrap_or(1);
+        let bbox = TextBox::new(0.0, bottom - 20.0, width - 2.0, bottom);
+        draw_text(&mut image, &format!("precip 0-{precip_scale}\" per {step_hours}h"), bbox, &scale_style);
     }
 
     image
 }
 
-pub fn measure_text(font: &Font, text: &str, font_size: f32) -> (f32, f32) {
-    let font_size = Scale::uniform(font_size);
-    let v_metrics = font.v_metrics(font_size);
+/// The shading for `precip_type`: diagonal lines for rain (or unknown), dots for snow, and
+/// crosshatching for mixed precipitation
+fn precip_fill(precip_type: Option<PrecipType>) -> Fill {
+    let black = image::Rgb([0u8, 0u8, 0u8]);
+    match precip_type {
+        None | Some(PrecipType::Rain) => Fill::new(Pattern::Diagonal, black),
+        Some(PrecipType::Snow) => Fill::new(Pattern::Dots, black),
+        Some(PrecipType::Mixed) => Fill::new(Pattern::Crosshatch, black),
+    }
+}
+
+/// Draws a small key in the bottom left corner of the graph with a swatch of the shading for each of
+/// `precip_types`
+fn draw_precip_legend(image: &mut RgbImage, precip_types: &[PrecipType], font: &FontChain) {
+    let black = image::Rgb([0u8, 0u8, 0u8]);
+    let white = image::Rgb([255u8, 255u8, 255u8]);
+    let swatch_size = 12u32;
+    let style = TextStyle::new(font, 16.0, black);
+
+    let mut x = 4u32;
+    let y = image.height() - swatch_size - 4;
+    for precip_type in precip_types {
+        let name = match precip_type {
+            PrecipType::Rain => "rain",
+            PrecipType::Snow => "snow",
+            PrecipType::Mixed => "mix",
+        };
+        let (text_width, _) = measure_text(font, name, style.size);
+        // a white background so the key can be read over the graph
+        let entry_width = swatch_size + 4 + text_width.ceil() as u32;
+        draw_filled_rect_mut(image, Rect::at(x as i32 - 2, y as i32 - 2).of_size(entry_width + 6, swatch_size + 4), white);
+        precip_fill(Some(*precip_type)).draw_swatch(image, x, y, swatch_size, black);
+        let bbox = TextBox::new((x + swatch_size + 4) as f32, y as f32 - 2.0, image.width() as f32, (y + swatch_size + 2) as f32);
+        draw_text(image, name, bbox, &TextStyle { v_align: VAlign::Center, ..style });
+        x += entry_width + 10;
+    }
+}
 
-    let xpad = 0f32;
-    let ypad = 0f32;
+/// The scale for the precipitation bars, which is the smallest of `PRECIP_SCALES_IN` that fits
+/// the biggest amount. Returns None if the forecast doesn't have any amounts.
+fn precip_bar_scale(forecast_data: &[ForecastPoint]) -> Option<f32> {
+    let max_precip = forecast_data.iter().filter_map(|p| p.precip_in).reduce(f32::max)?;
+    Some(PRECIP_SCALES_IN.iter().copied()
+        .find(|s| max_precip <= *s)
+        .unwrap_or(max_precip))
+}
 
-    let glyphs: Vec<_> = font
-        .layout(text, font_size, point(xpad, ypad + v_metrics.ascent))
-        .collect();
+/// Draws a strip of arrows pointing in the direction the wind is blowing, lined up with the points
+/// on the graph from `draw_5day_graph`. Longer arrows are stronger winds, and winds of 15mph and
+/// over are drawn in red.
+pub fn draw_wind_arrows(forecast: &ForecastWindow, width: i64, height: i64, options: &GraphOptions) -> RgbImage {
+    let mut image = RgbImage::from_fn(width as u32, height as u32, |_, _| -> Rgb<u8> { Rgb([255u8, 255u8, 255u8]) });
+    let black = image::Rgb([0u8, 0u8, 0u8]);
+    let red = image::Rgb([255u8, 0u8, 0u8]);
+
+    let height = height as f32;
+    let width = width as f32;
+
+    let forecast_data = forecast.filtered_forecast();
+    let axis = graph_time_axis(&forecast_data, forecast.filtered_step(), width, options);
+    // skip points if the arrows would be too close together
+    let step = (height / axis.horiz_spacing).ceil().max(1.0) as usize;
+
+    let cy = height / 2.0;
+    for p in forecast_data.iter().step_by(step) {
+        let (wind_speed, direction) = match (p.wind_speed, p.wind_direction) {
+            (Some(wind_speed), Some(direction)) => (wind_speed, direction),
+            _ => continue,
+        };
+        let cx = axis.x(p.time).max(height / 2.0).min(width - height / 2.0);
+        let color = if wind_speed >= 15 { red } else { black };
+        let length = (height - 2.0) * (0.5 + (wind_speed as f32 / 30.0).min(0.5));
+
+        // the direction is where the wind is coming from, so point the other way. y points down.
+        let angle = (direction + 180.0).to_radians();
+        let (dx, dy) = (angle.sin(), -angle.cos());
+        let tail = (cx - dx*length/2.0, cy - dy*length/2.0);
+        let tip = (cx + dx*length/2.0, cy + dy*length/2.0);
+        draw_line_segment_mut(&mut image, tail, tip, color);
+
+        let head = length / 3.0;
+        for side in [-1.0f32, 1.0] {
+            let head_angle = angle + side * 150f32.to_radians();
+            let end = (tip.0 + head_angle.sin()*head, tip.1 - head_angle.cos()*head);
+            draw_line_segment_mut(&mut image, tip, end, color);
+        }
+    }
+
+    image
+}
+
+/// Draws an arrow pointing up, across or down for which way the pressure is going, with the change
+/// in inches of mercury next to it. Rapid changes are drawn in red.
+pub fn draw_pressure_tendency(change_inhg: f32, width: i64, height: i64, font: &FontChain) -> RgbImage {
+    let mut image = RgbImage::from_fn(width as u32, height as u32, |_, _| -> Rgb<u8> { Rgb([255u8, 255u8, 255u8]) });
+    let black = image::Rgb([0u8, 0u8, 0u8]);
+    let red = image::Rgb([255u8, 0u8, 0u8]);
 
-    let height = (v_metrics.ascent - v_metrics.descent).ceil();
-    let width = {
-            let min_x = glyphs
-                    .first()
-                    .map(|g| g.pixel_bounding_box().unwrap().min.x)
-                    .unwrap();
-            let max_x = glyphs
-                    .last()
-                    .map(|g| g.pixel_bounding_box().unwrap().max.x)
-                    .unwrap();
-            (max_x - min_x) as f32
+    let height = height as f32;
+    let width = width as f32;
+    let color = if change_inhg.abs() >= PRESSURE_RAPID_CHANGE_INHG { red } else { black };
+
+    // y points down
+    let (dx, dy): (f32, f32) = match PressureTrend::from_change(change_inhg) {
+        PressureTrend::Rising => (0.0, -1.0),
+        PressureTrend::Steady => (1.0, 0.0),
+        PressureTrend::Falling => (0.0, 1.0),
     };
+    let center = height / 2.0;
+    let length = height - 4.0;
+    let head = length / 3.0;
+    // drawn twice a pixel apart so it's as heavy as the text next to it
+    for offset in [0.0, 1.0] {
+        let (ox, oy) = (dy.abs() * offset, dx.abs() * offset);
+        let tail = (center - dx*length/2.0 + ox, center - dy*length/2.0 + oy);
+        let tip = (center + dx*length/2.0 + ox, center + dy*length/2.0 + oy);
+        draw_line_segment_mut(&mut image, tail, tip, color);
+        for side in [-1.0f32, 1.0] {
+            let angle = dy.atan2(dx) + side * 150f32.to_radians();
+            let end = (tip.0 + angle.cos()*head, tip.1 + angle.sin()*head);
+            draw_line_segment_mut(&mut image, tip, end, color);
+        }
+    }
+
+    let text_left = height + 4.0;
+    if text_left < width {
+        let bbox = TextBox::new(text_left, 0.0, width, height);
+        draw_text(&mut image, &format!("{change_inhg:+.2}"), bbox, &TextStyle {
+            v_align: VAlign::Center,
+            overflow: Overflow::ShrinkToFit { min_size: 12.0 },
+            ..TextStyle::new(font, height, color)
+        });
+    }
 
-    (width, height)
+    image
 }
 
-// TODO: figure out a way to reduce duplication of all these functions here? I guess just pass an
-// align enum to a single draw_text method or something
+/// Draws a column for each of the forecast points, with the hour, an icon for the weather, the
+/// temperature and the rain probability from top to bottom. Meant for the next 12 hours, so the
+/// columns don't get too narrow.
+pub fn draw_hourly_strip(points: &[ForecastPoint], width: i64, height: i64, font: &FontChain,
+        location: Option<&Location>) -> RgbImage {
+    let mut image = RgbImage::from_fn(width as u32, height as u32, |_, _| -> Rgb<u8> { Rgb([255u8, 255u8, 255u8]) });
+    let black = image::Rgb([0u8, 0u8, 0u8]);
+    let red = image::Rgb([255u8, 0u8, 0u8]);
+
+    if points.is_empty() {
+        return image;
+    }
+    let height = height as f32;
+    let column_width = width as f32 / points.len() as f32;
+    // the icon gets the middle 40% of the height and the text gets 20% each
+    let row_height = height / 5.0;
+    let icon_size = (row_height * 2.0).min(column_width - 4.0).max(1.0);
+    let style = TextStyle {
+        h_align: HAlign::Center,
+        v_align: VAlign::Center,
+        overflow: Overflow::ShrinkToFit { min_size: 10.0 },
+        ..TextStyle::new(font, (row_height * 0.9).min(24.0), black)
+    };
+
+    for (i, point) in points.iter().enumerate() {
+        let left = i as f32 * column_width;
+        let right = left + column_width;
+        // leave a gap between the columns so the labels don't run together
+        let row = |n: f32| TextBox::new(left + 3.0, n * row_height, right - 3.0, (n + 1.0) * row_height);
+
+        // a line between the days
+        if i > 0 && point.time.date_naive() != points[i - 1].time.date_naive() {
+            draw_line_segment_mut(&mut image, (left, 0.0), (left, height - 1.0), black);
+        }
+
+        draw_text(&mut image, &point.time.format("%-I%P").to_string(), row(0.0), &style);
 
-pub fn draw_text_centered(image: &mut RgbImage, text: &str, x: f32, y: f32, font: &Font, scale: f32) {
+        let night = location.is_some_and(|location| is_night(&point.time, location));
+        let icon_x = left + (column_width - icon_size) / 2.0;
+        let icon_y = row_height + (row_height * 2.0 - icon_size) / 2.0;
+        draw_weather_icon(&mut image, WeatherIcon::for_point(point), night,
+            icon_x as i32, icon_y as i32, icon_size as i32);
+
+        draw_text(&mut image, &format!("{}°", point.temp_f), row(3.0), &style);
+        draw_text(&mut image, &format!("{}%", point.rain_prob), row(4.0),
+            &TextStyle { color: red, ..style });
+    }
+
+    image
+}
+
+/// Draws the detailed forecast for each period next to its name, laid out like the task list.
+/// Periods are drawn in order until there's no room left, and the last one is cut off with an
+/// ellipsis if it doesn't fit.
+pub fn draw_forecast_text(periods: &[ForecastPeriod], width: i64, height: i64, font: &FontChain) -> RgbImage {
+    let mut image = RgbImage::from_fn(width as u32, height as u32, |_, _| -> Rgb<u8> { Rgb([255u8, 255u8, 255u8]) });
     let black = image::Rgb([0u8, 0u8, 0u8]);
-    let (text_width, text_height) = measure_text(&font, &text, scale);
-    let scale = Scale::uniform(scale);
-    let text_x = (x - text_width/2.0).ceil() as i32;
-    let text_y = (y - text_height/2.0).ceil() as i32;
+    let red = image::Rgb([255u8, 0u8, 0u8]);
+
+    let height = height as f32;
+    let width = width as f32;
+    let name_width = 150.0;
+    let style = TextStyle { overflow: Overflow::Ellipsis, ..TextStyle::new(font, 20.0, black) };
+    let scale = Scale::uniform(style.size);
+    let (_, line_height) = text::measure_text(font, "", scale);
+    let line_spacing = line_height + font.v_metrics(scale).line_gap.ceil();
+
+    let mut y = 0.0;
+    for period in periods {
+        let (name, details) = match (&period.period_name, &period.long_desc) {
+            (Some(name), Some(details)) => (name, details),
+            _ => continue,
+        };
+        if y + line_height > height {
+            break;
+        }
+        let max_lines = ((height - y - line_height) / line_spacing) as usize + 1;
 
-    draw_text_mut(image, black, text_x, text_y, scale, &font, &text);
+        draw_text(&mut image, name, TextBox::new(0.0, y, name_width - 10.0, height),
+            &TextStyle { color: red, overflow: Overflow::ShrinkToFit { min_size: 14.0 }, ..style });
+        let drawn = draw_text(&mut image, details, TextBox::new(name_width, y, width, height),
+            &TextStyle { max_lines, ..style });
+        y = drawn.bottom + 8.0;
+    }
+
+    image
 }
 
-pub fn draw_text_left(image: &mut RgbImage, text: &str, x: f32, y: f32, font: &Font, scale: f32) {
+/// Draws the moon phase as an icon `height` pixels across, with the name of the phase next to it.
+/// The lit part of the moon is white, the dark part is black and the outline is red.
+pub fn draw_moon_phase(moon: &MoonPhase, width: i64, height: i64, font: &FontChain) -> RgbImage {
+    let mut image = RgbImage::from_fn(width as u32, height as u32, |_, _| -> Rgb<u8> { Rgb([255u8, 255u8, 255u8]) });
     let black = image::Rgb([0u8, 0u8, 0u8]);
-    let scale = Scale::uniform(scale);
+    let red = image::Rgb([255u8, 0u8, 0u8]);
+
+    let radius = height as f32 / 2.0 - 1.0;
+    let center = height as f32 / 2.0;
+    // the terminator is an ellipse whose width depends on how far through the cycle the moon is
+    let terminator = (moon.cycle_fraction() as f32 * std::f32::consts::TAU).cos();
+    for y in 0..height as u32 {
+        for x in 0..height as u32 {
+            let nx = (x as f32 + 0.5 - center) / radius;
+            let ny = (y as f32 + 0.5 - center) / radius;
+            let dist = (nx*nx + ny*ny).sqrt();
+            if dist > 1.0 {
+                continue;
+            }
+            if dist > 1.0 - 1.5 / radius {
+                image.put_pixel(x, y, red);
+                continue;
+            }
+
+            // lit from the right while waxing and from the left while waning, as seen from the
+            // northern hemisphere
+            let edge = terminator * (1.0 - ny*ny).sqrt();
+            let lit = if moon.is_waxing() { nx > edge } else { nx < -edge };
+            if !lit {
+                image.put_pixel(x, y, black);
+            }
+        }
+    }
+
+    let text_left = height as f32 + 8.0;
+    if text_left < width as f32 {
+        let bbox = TextBox::new(text_left, 0.0, width as f32, height as f32);
+        draw_text(&mut image, moon.name(), bbox, &TextStyle {
+            v_align: VAlign::Center,
+            max_lines: 2,
+            overflow: Overflow::Fit { min_size: 14.0 },
+            ..TextStyle::new(font, 20.0, black)
+        });
+    }
+
+    image
+}
+
+/// Fraction of the data's range added above and below it
+const AXIS_PADDING: f32 = 0.05;
+/// The smallest range an axis will cover, so that flat data isn't blown up to fill the graph
+const AXIS_MIN_SPAN: f32 = 10.0;
+/// Roughly how many steps the axis should be split into
+const AXIS_TARGET_STEPS: f32 = 8.0;
+
+/// The range of a y axis, padded around the data and rounded out to multiples of a nice step
+/// (1, 2 or 5 times a power of 10)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AxisRange {
+    pub min: f32,
+    pub max: f32,
+    pub step: f32,
+}
+
+impl AxisRange {
+    pub fn new(min_value: i32, max_value: i32) -> AxisRange {
+        let (min_value, max_value) = (min_value as f32, max_value as f32);
+        let padding = (max_value - min_value) * AXIS_PADDING;
+        let mut min = min_value - padding;
+        let mut max = max_value + padding;
+        if max - min < AXIS_MIN_SPAN {
+            let center = (min + max) / 2.0;
+            min = center - AXIS_MIN_SPAN / 2.0;
+            max = center + AXIS_MIN_SPAN / 2.0;
+        }
 
-    draw_text_mut(image, black, x as i32, y as i32, scale, &font, &text);
+        let rough_step = (max - min) / AXIS_TARGET_STEPS;
+        let magnitude = 10f32.powf(rough_step.log10().floor());
+        let step = match rough_step / magnitude {
+            n if n <= 1.0 => 1.0,
+            n if n <= 2.0 => 2.0,
+            n if n <= 5.0 => 5.0,
+            _ => 10.0,
+        } * magnitude;
+
+        // adding zero turns -0 into 0, so it isn't labelled as -0
+        AxisRange {
+            min: (min / step).floor() * step + 0.0,
+            max: (max / step).ceil() * step + 0.0,
+            step,
+        }
+    }
 }
 
-pub fn draw_text_right(image: &mut RgbImage, text: &str, x: f32, y: f32, font: &Font, scale: f32, color: Rgb<u8>) {
-    let (text_width, _text_height) = measure_text(&font, &text, scale);
-    let scale = Scale::uniform(scale);
-    let text_x = x - text_width;
+/// The time axis for the forecast graph and anything lined up with it. It covers the forecast
+/// points, plus the day before them if the history is shown.
+fn graph_time_axis(points: &[ForecastPoint], step: Duration, width: f32, options: &GraphOptions) -> TimeAxis {
+    let start = points.first().expect("no forecast data to graph").time;
+    let end = points.last().unwrap().time;
+    let start = if options.history { start - Duration::days(1) } else { start };
+    TimeAxis::new(start, end, step, width)
+}
 
-    draw_text_mut(image, color, text_x as i32, y as i32, scale, &font, &text);
+/// The temperature axis for the forecast graph, covering both the forecast and any observations
+/// drawn on it
+pub fn graph_temp_axis(forecast: &ForecastWindow, observations: &[Observation]) -> AxisRange {
+    let (min_temp, max_temp) = forecast.week_minmax_temps();
+    let min_temp = observations.iter().map(|o| o.temp_f).fold(min_temp, i32::min);
+    let max_temp = observations.iter().map(|o| o.temp_f).fold(max_temp, i32::max);
+    // pad the temp values so that the temperature graph doesn't go to right to the border
+    AxisRange::new(min_temp, max_temp)
 }
 
-pub fn draw_text_left_color(image: &mut RgbImage, text: &str, x: f32, y: f32, font: &Font, scale: f32, color: Rgb<u8>) {
-    let scale = Scale::uniform(scale);
+pub fn measure_text(font: &FontChain, text: &str, font_size: f32) -> (f32, f32) {
+    text::measure_text(font, text, Scale::uniform(font_size))
+}
 
-    draw_text_mut(image, color, x as i32, y as i32, scale, &font, &text);
+/// Horizontal alignment of text inside of a `TextBox`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HAlign {
+    Left,
+    Center,
+    Right,
 }
 
+/// Vertical alignment of text inside of a `TextBox`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VAlign {
+    Top,
+    Center,
+    Bottom,
+}
 
-pub fn draw_text_bottom(image: &mut RgbImage, text: &str, x: f32, y: f32, font: &Font, scale: f32, color: Rgb<u8>) {
-    let (_text_width, text_height) = measure_text(&font, &text, scale);
-    let scale = Scale::uniform(scale);
+/// What to do when text doesn't fit in its `TextBox`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overflow {
+    /// Draw the text as-is, even if it goes outside of the box
+    Visible,
+    /// Wrap at word boundaries. Lines past `max_lines` are dropped.
+    Wrap,
+    /// Wrap at word boundaries, and cut off the last line with an ellipsis if there is text left
+    /// over.
+    Ellipsis,
+    /// Reduce the font size until the text fits, but not below `min_size`. If it still doesn't fit
+    /// at the minimum size, the text is cut off with an ellipsis.
+    ShrinkToFit { min_size: f32 },
+    /// Use the largest size down to `min_size` that fits on a single line, and if there isn't one,
+    /// the largest size that fits when wrapped to `max_lines`. If it still doesn't fit the text is
+    /// cut off with an ellipsis.
+    Fit { min_size: f32 },
+}
 
-    draw_text_mut(image, color, x as i32, (y-text_height) as i32, scale, &font, &text);
+/// The region text is laid out in, in pixel coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextBox {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
 }
 
-pub fn draw_text_bottom_right(image: &mut RgbImage, text: &str, x: f32, y: f32, font: &Font, scale: f32, color: Rgb<u8>) {
-    let (text_width, text_height) = measure_text(&font, &text, scale);
-    let scale = Scale::uniform(scale);
+impl TextBox {
+    pub fn new(left: f32, top: f32, right: f32, bottom: f32) -> TextBox {
+        TextBox { left, top, right, bottom }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> f32 {
+        self.bottom - self.top
+    }
+}
 
-    draw_text_mut(image, color, (x-text_width) as i32, (y-text_height) as i32, scale, &font, &text);
+#[derive(Clone, Copy)]
+pub struct TextStyle<'a> {
+    pub font: &'a FontChain,
+    pub size: f32,
+    pub color: Rgb<u8>,
+    pub h_align: HAlign,
+    pub v_align: VAlign,
+    pub max_lines: usize,
+    pub overflow: Overflow,
+}
+
+impl<'a> TextStyle<'a> {
+    /// A single line of text aligned to the top left of its box, drawn as-is.
+    pub fn new(font: &'a FontChain, size: f32, color: Rgb<u8>) -> TextStyle<'a> {
+        TextStyle {
+            font,
+            size,
+            color,
+            h_align: HAlign::Left,
+            v_align: VAlign::Top,
+            max_lines: 1,
+            overflow: Overflow::Visible,
+        }
+    }
+}
+
+/// Lays out `text` inside of `bbox` according to `style` and draws it onto `image`.
+///
+/// Returns the region the text was actually drawn in.
+pub fn draw_text(image: &mut RgbImage, text: &str, bbox: TextBox, style: &TextStyle) -> TextBox {
+    let font = style.font;
+    let TextLayout { size, lines, .. } = layout_text(text, bbox, style);
+    let scale = Scale::uniform(size);
+
+    let (_width, line_height) = text::measure_text(font, "", scale);
+    let line_spacing = line_height + font.v_metrics(scale).line_gap.ceil();
+    let total_height = line_height + line_spacing * (lines.len().max(1) - 1) as f32;
+
+    let top = match style.v_align {
+        VAlign::Top => bbox.top,
+        VAlign::Center => (bbox.top + bbox.height()/2.0 - total_height/2.0).ceil(),
+        VAlign::Bottom => bbox.bottom - total_height,
+    };
+
+    let mut drawn = TextBox::new(bbox.right, top, bbox.left, top + total_height);
+    for (i, line) in lines.iter().enumerate() {
+        let (line_width, _) = text::measure_text(font, line, scale);
+        let x = match style.h_align {
+            HAlign::Left => bbox.left,
+            HAlign::Center => (bbox.left + bbox.width()/2.0 - line_width/2.0).ceil(),
+            HAlign::Right => bbox.right - line_width,
+        };
+        let y = top + line_spacing * i as f32;
+
+        draw_text_mut(image, style.color, x as i32, y as i32, scale, font, line);
+        drawn.left = drawn.left.min(x);
+        drawn.right = drawn.right.max(x + line_width);
+    }
+
+    drawn
+}
+
+/// Whether `text` can be drawn in `bbox` with `style` without being cut off or going outside of the
+/// box.
+pub fn text_fits(text: &str, bbox: TextBox, style: &TextStyle) -> bool {
+    !layout_text(text, bbox, style).truncated
+}
+
+struct TextLayout {
+    size: f32,
+    lines: Vec<String>,
+    /// Whether the text had to be cut off, or doesn't fit in the box
+    truncated: bool,
+}
+
+/// Returns the font size to use and the text broken into lines according to the style's overflow
+/// policy.
+fn layout_text(text: &str, bbox: TextBox, style: &TextStyle) -> TextLayout {
+    let font = style.font;
+    let max_lines = style.max_lines.max(1);
+
+    match style.overflow {
+        Overflow::Visible => {
+            let (width, height) = text::measure_text(font, text, Scale::uniform(style.size));
+            let truncated = width > bbox.width() || height > bbox.height();
+            TextLayout { size: style.size, lines: vec![text.to_string()], truncated }
+        }
+        Overflow::Wrap => {
+            let mut lines = text::wrap_text(font, text, Scale::uniform(style.size), bbox.width());
+            let truncated = lines.len() > max_lines;
+            lines.truncate(max_lines);
+            TextLayout { size: style.size, lines, truncated }
+        }
+        Overflow::Ellipsis => {
+            ellipsis_layout(text, bbox, style.font, style.size, max_lines)
+        }
+        Overflow::ShrinkToFit { min_size } => {
+            font_sizes(style.size, min_size)
+                .find_map(|size| fit_lines(text, bbox, font, size, max_lines))
+                .unwrap_or_else(|| ellipsis_layout(text, bbox, font, min_size, max_lines))
+        }
+        Overflow::Fit { min_size } => {
+            font_sizes(style.size, min_size)
+                .find_map(|size| fit_lines(text, bbox, font, size, 1))
+                .or_else(|| font_sizes(style.size, min_size)
+                    .find_map(|size| fit_lines(text, bbox, font, size, max_lines)))
+                .unwrap_or_else(|| ellipsis_layout(text, bbox, font, min_size, max_lines))
+        }
+    }
+}
+
+/// Font sizes from `max_size` down to `min_size` in 1px steps
+fn font_sizes(max_size: f32, min_size: f32) -> impl Iterator<Item = f32> {
+    let steps = (max_size - min_size).max(0.0).ceil() as usize;
+    (0..=steps).map(move |i| (max_size - i as f32).max(min_size))
+}
+
+/// Returns the wrapped layout at `size` if it fits in `max_lines` and in the box's height
+fn fit_lines(text: &str, bbox: TextBox, font: &FontChain, size: f32, max_lines: usize) -> Option<TextLayout> {
+    let scale = Scale::uniform(size);
+    let lines = text::wrap_text(font, text, scale, bbox.width());
+    let (_, line_height) = text::measure_text(font, "", scale);
+    let line_spacing = line_height + font.v_metrics(scale).line_gap.ceil();
+    let height = line_height + line_spacing * (lines.len() - 1) as f32;
+
+    if lines.len() <= max_lines && height <= bbox.height() {
+        Some(TextLayout { size, lines, truncated: false })
+    }
+    else {
+        None
+    }
+}
+
+fn ellipsis_layout(text: &str, bbox: TextBox, font: &FontChain, size: f32, max_lines: usize) -> TextLayout {
+    let scale = Scale::uniform(size);
+    let truncated = text::wrap_text(font, text, scale, bbox.width()).len() > max_lines;
+    let lines = text::wrap_ellipsis(font, text, scale, bbox.width(), max_lines);
+    TextLayout { size, lines, truncated }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LONG_TEXT: &str = "pick up the dry cleaning before the store closes on saturday";
+
+    #[test]
+    fn test_layout_text() {
+        let font = crate::Fonts::default().tasks;
+        let style = TextStyle::new(&font, 24.0, Rgb([0u8, 0u8, 0u8]));
+        let bbox = TextBox::new(0.0, 0.0, 200.0, 200.0);
+        let one_line = TextBox::new(0.0, 0.0, 200.0, 30.0);
+
+        // wrapping drops the lines past `max_lines`
+        let layout = layout_text(LONG_TEXT, bbox, &TextStyle { overflow: Overflow::Wrap, max_lines: 2, ..style });
+        assert_eq!(layout.lines, vec!["pick up the dry", "cleaning before"]);
+        assert!(layout.truncated);
+        let layout = layout_text(LONG_TEXT, bbox, &TextStyle { overflow: Overflow::Wrap, max_lines: 10, ..style });
+        assert_eq!(layout.lines.len(), 4);
+        assert_eq!(layout.lines.join(" "), LONG_TEXT);
+        assert!(!layout.truncated);
+
+        // the last line is cut off with an ellipsis
+        let layout = layout_text(LONG_TEXT, bbox, &TextStyle { overflow: Overflow::Ellipsis, max_lines: 2, ..style });
+        assert_eq!(layout.lines, vec!["pick up the dry", "cleaning before…"]);
+        assert!(layout.truncated);
+
+        // shrinking stops at the minimum size and then uses an ellipsis
+        let shrink = TextStyle { overflow: Overflow::ShrinkToFit { min_size: 14.0 }, ..style };
+        let layout = layout_text(LONG_TEXT, one_line, &shrink);
+        assert_eq!(layout.size, 14.0);
+        assert_eq!(layout.lines, vec!["pick up the dry cleaning…"]);
+        assert!(layout.truncated);
+        // and only goes as small as it needs to
+        let layout = layout_text("dry cleaning today", one_line, &TextStyle { size: 40.0, ..shrink });
+        assert_eq!(layout.size, 22.0);
+        assert!(!layout.truncated);
+
+        // fitting prefers a smaller size on one line, unless it's below the minimum
+        let fit = TextStyle { size: 36.0, max_lines: 2, overflow: Overflow::Fit { min_size: 12.0 }, ..style };
+        let two_lines = TextBox::new(0.0, 0.0, 200.0, 100.0);
+        let layout = layout_text("dry cleaning today", two_lines, &fit);
+        assert_eq!((layout.size, layout.lines.len()), (22.0, 1));
+        let layout = layout_text("dry cleaning today", two_lines, &TextStyle { overflow: Overflow::Fit { min_size: 24.0 }, ..fit });
+        assert_eq!(layout.size, 34.0);
+        assert_eq!(layout.lines, vec!["dry cleaning", "today"]);
+        assert!(text_fits("dry cleaning today", two_lines, &fit));
+        assert!(!text_fits(LONG_TEXT, one_line, &shrink));
+    }
+
+    #[test]
+    fn test_draw_text_alignment() {
+        let font = crate::Fonts::default().tasks;
+        let black = Rgb([0u8, 0u8, 0u8]);
+        let white = Rgb([255u8, 255u8, 255u8]);
+        let style = TextStyle::new(&font, 24.0, black);
+        let bbox = TextBox::new(10.0, 10.0, 190.0, 90.0);
+        let (width, height) = measure_text(&font, "hi", 24.0);
+
+        let draw = |h_align, v_align| {
+            let mut image = RgbImage::from_pixel(200, 100, white);
+            let drawn = draw_text(&mut image, "hi", bbox, &TextStyle { h_align, v_align, ..style });
+            // all of the ink is inside the box that was returned, give or take a pixel of
+            // antialiasing
+            for (x, y, p) in image.enumerate_pixels() {
+                let (x, y) = (x as f32, y as f32);
+                if *p != white {
+                    assert!(drawn.left - 1.0 <= x && x <= drawn.right && drawn.top - 1.0 <= y && y <= drawn.bottom,
+                        "ink outside {drawn:?} at {x} {y}");
+                }
+            }
+            assert!(image.pixels().any(|p| *p == black));
+            drawn
+        };
+
+        assert_eq!(draw(HAlign::Left, VAlign::Top), TextBox::new(10.0, 10.0, 10.0 + width, 10.0 + height));
+        assert_eq!(draw(HAlign::Right, VAlign::Bottom), TextBox::new(190.0 - width, 90.0 - height, 190.0, 90.0));
+        let centered = draw(HAlign::Center, VAlign::Center);
+        assert!(((centered.left + centered.right) / 2.0 - 100.0).abs() <= 1.0, "{centered:?}");
+        assert!(((centered.top + centered.bottom) / 2.0 - 50.0).abs() <= 1.0, "{centered:?}");
+        assert_eq!((centered.width(), centered.height()), (width, height));
+
+        // wrapped lines are each aligned on their own, and the box covers all of them
+        let mut image = RgbImage::from_pixel(200, 100, white);
+        let wrapped = draw_text(&mut image, "dry cleaning today", TextBox::new(0.0, 0.0, 150.0, 100.0),
+            &TextStyle { h_align: HAlign::Right, max_lines: 2, overflow: Overflow::Wrap, ..style });
+        assert_eq!(wrapped.right, 150.0);
+        assert_eq!(wrapped.left, 150.0 - measure_text(&font, "dry cleaning", 24.0).0);
+        assert!(wrapped.height() > height * 1.5);
+    }
+
+    #[test]
+    fn test_axis_range() {
+        let range = |min, max| {
+            let axis = AxisRange::new(min, max);
+            (axis.min, axis.max, axis.step)
+        };
+        // a typical week, padded a bit and rounded out to the step
+        assert_eq!(range(51, 59), (50.0, 60.0, 2.0));
+        // below zero the padding still goes outwards
+        assert_eq!(range(-20, -5), (-25.0, 0.0, 5.0));
+        // and the labels don't come out as -0
+        assert_eq!(AxisRange::new(-20, -5).max.to_string(), "0");
+        // a constant temperature is given the minimum span around it, even at zero
+        assert_eq!(range(50, 50), (44.0, 56.0, 2.0));
+        assert_eq!(range(-7, -7), (-12.0, -2.0, 2.0));
+        assert_eq!(range(0, 0), (-6.0, 6.0, 2.0));
+        // wide ranges get bigger steps
+        assert_eq!(range(-55, 50), (-80.0, 60.0, 20.0));
+        assert_eq!(range(-30, 110), (-40.0, 120.0, 20.0));
+    }
 }
@@ -1,5 +1,7 @@
 use std::path::Path;
 
+use crate::{ArchiveOptions, FontConfig, ForecastOptions, PictureSource, RenderOptions};
+
 #[derive(Debug, Clone)]
 pub struct EnvData {
     pub local_timezone: chrono_tz::Tz,
@@ -8,6 +10,16 @@ pub struct EnvData {
     pub weather_station: String,
     pub weather_office: String,
     pub weather_gridpoint: String,
+    /// Optional directory or url of pictures to show on the display
+    pub picture_source: Option<PictureSource>,
+    /// Fonts to use for each kind of text
+    pub fonts: FontConfig,
+    /// How many days of the forecast to show and how to smooth it
+    pub forecast: ForecastOptions,
+    /// Optional layout and display settings
+    pub render_options: RenderOptions,
+    /// Where to keep a record of each run's observation and forecast, if anywhere
+    pub archive: Option<ArchiveOptions>,
 }
 
 impl EnvData {
@@ -23,6 +35,11 @@ impl EnvData {
             weather_station: data["weather_station"].as_str().unwrap().into(),
             weather_office: data["weather_office"].as_str().unwrap().into(),
             weather_gridpoint: data["weather_gridpoint"].as_str().unwrap().into(),
+            picture_source: data["picture_source"].as_str().map(PictureSource::from),
+            fonts: FontConfig::from_json(&data["fonts"]),
+            forecast: ForecastOptions::from_json(&data["forecast"]),
+            render_options: RenderOptions::from_json(&data),
+            archive: ArchiveOptions::from_json(&data["archive"]),
         }
     }
 }
@@ -0,0 +1,241 @@
+/// Font loading and configuration for each kind of text drawn on the display
+
+use std::path::PathBuf;
+
+use rusttype::{Font, Scale, VMetrics};
+use serde_json::Value;
+
+use crate::{BitmapFont, parse_bdf, parse_pcf};
+
+const COMFORTAA_REGULAR: &[u8] = include_bytes!("../fonts/Comfortaa-Regular.ttf");
+const COMFORTAA_MEDIUM: &[u8] = include_bytes!("../fonts/Comfortaa-Medium.ttf");
+
+/// Where to load a font from
+#[derive(Debug, Clone, PartialEq)]
+pub enum FontSource {
+    /// The built-in Comfortaa Regular
+    Regular,
+    /// The built-in Comfortaa Medium
+    Medium,
+    /// A TrueType/OpenType font file, or a BDF/PCF bitmap font if the file ends in `.bdf` or
+    /// `.pcf`
+    File(PathBuf),
+}
+
+impl FontSource {
+    /// Parses either `{"weight": "regular"|"medium"}` or `{"file": "path/to/font.ttf"}`
+    fn from_json(value: &Value) -> Option<FontSource> {
+        if let Some(path) = value["file"].as_str() {
+            return Some(FontSource::File(path.into()));
+        }
+
+        match value["weight"].as_str()? {
+            "regular" => Some(FontSource::Regular),
+            "medium" => Some(FontSource::Medium),
+            weight => panic!("unknown font weight {weight}, expected regular or medium"),
+        }
+    }
+
+    fn load(&self) -> FontFace {
+        let font = match self {
+            FontSource::Regular => Font::try_from_bytes(COMFORTAA_REGULAR),
+            FontSource::Medium => Font::try_from_bytes(COMFORTAA_MEDIUM),
+            FontSource::File(path) => {
+                let data = std::fs::read(path)
+                    .unwrap_or_else(|e| panic!("failed to read font file {path:?}: {e}"));
+                match path.extension().and_then(|ext| ext.to_str()) {
+                    Some("bdf") => {
+                        let data = String::from_utf8(data).expect("bdf font was not valid utf-8");
+                        return FontFace::Bitmap(parse_bdf(&data));
+                    }
+                    Some("pcf") => return FontFace::Bitmap(parse_pcf(&data)),
+                    _ => Font::try_from_vec(data),
+                }
+            }
+        };
+
+        FontFace::TrueType(font.expect("failed to open font"))
+    }
+}
+
+/// Which font to use for each role, plus fallback fonts used for glyphs missing from them.
+#[derive(Debug, Clone)]
+pub struct FontConfig {
+    /// The large current temperature
+    pub temperature: FontSource,
+    /// The current conditions description
+    pub description: FontSource,
+    /// The date/time and today's high and low
+    pub header: FontSource,
+    /// Labels on and around the forecast graph
+    pub graph: FontSource,
+    /// The task list
+    pub tasks: FontSource,
+    /// Fonts tried in order for glyphs that the role's font doesn't have
+    pub fallback: Vec<PathBuf>,
+}
+
+impl Default for FontConfig {
+    fn default() -> FontConfig {
+        FontConfig {
+            temperature: FontSource::Regular,
+            description: FontSource::Regular,
+            header: FontSource::Regular,
+            graph: FontSource::Regular,
+            tasks: FontSource::Regular,
+            fallback: Vec::new(),
+        }
+    }
+}
+
+impl FontConfig {
+    /// Reads the `fonts` section of the env data. Any missing roles use Comfortaa Regular.
+    pub fn from_json(data: &Value) -> FontConfig {
+        let default = FontConfig::default();
+        let role = |name: &str, default: FontSource| {
+            FontSource::from_json(&data[name]).unwrap_or(default)
+        };
+
+        let fallback = data["fallback"].as_array()
+            .map(|paths| paths.iter()
+                .map(|p| p.as_str().expect("font fallback entries must be file paths").into())
+                .collect())
+            .unwrap_or_default();
+
+        FontConfig {
+            temperature: role("temperature", default.temperature),
+            description: role("description", default.description),
+            header: role("header", default.header),
+            graph: role("graph", default.graph),
+            tasks: role("tasks", default.tasks),
+            fallback,
+        }
+    }
+}
+
+/// A single loaded font, either drawn from its outlines with rusttype or from its bitmaps
+#[derive(Clone)]
+pub enum FontFace {
+    TrueType(Font<'static>),
+    Bitmap(BitmapFont),
+}
+
+impl FontFace {
+    pub fn has_glyph(&self, c: char) -> bool {
+        match self {
+            // glyph 0 is the "notdef" glyph used for missing characters
+            FontFace::TrueType(font) => font.glyph(c).id().0 != 0,
+            FontFace::Bitmap(font) => font.glyph(c).is_some(),
+        }
+    }
+
+    pub fn v_metrics(&self, scale: Scale) -> VMetrics {
+        match self {
+            FontFace::TrueType(font) => font.v_metrics(scale),
+            FontFace::Bitmap(font) => {
+                let pixel_scale = font.pixel_scale(scale.y);
+                VMetrics {
+                    ascent: (font.ascent * pixel_scale) as f32,
+                    descent: -(font.descent * pixel_scale) as f32,
+                    line_gap: 0.0,
+                }
+            }
+        }
+    }
+}
+
+/// A list of fonts, where each glyph is drawn from the first font that has it. The first font
+/// also determines the line metrics.
+#[derive(Clone)]
+pub struct FontChain {
+    fonts: Vec<FontFace>,
+}
+
+impl FontChain {
+    pub fn new(primary: FontFace, fallback: Vec<FontFace>) -> FontChain {
+        let mut fonts = vec![primary];
+        fonts.extend(fallback);
+        FontChain { fonts }
+    }
+
+    pub fn primary(&self) -> &FontFace {
+        &self.fonts[0]
+    }
+
+    pub fn v_metrics(&self, scale: Scale) -> VMetrics {
+        self.primary().v_metrics(scale)
+    }
+
+    /// Returns the first font in the chain with a glyph for `c`
+    pub fn font_for(&self, c: char) -> Option<&FontFace> {
+        self.fonts.iter().find(|font| font.has_glyph(c))
+    }
+}
+
+/// The loaded fonts for each text role
+#[derive(Clone)]
+pub struct Fonts {
+    pub temperature: FontChain,
+    pub description: FontChain,
+    pub header: FontChain,
+    pub graph: FontChain,
+    pub tasks: FontChain,
+}
+
+impl Fonts {
+    /// Loads all of the fonts in `config`. Font files have to be read before the sandbox is
+    /// applied.
+    pub fn load(config: &FontConfig) -> Fonts {
+        let fallback: Vec<FontFace> = config.fallback.iter()
+            .map(|path| FontSource::File(path.clone()).load())
+            .collect();
+        let chain = |source: &FontSource| FontChain::new(source.load(), fallback.clone());
+
+        Fonts {
+            temperature: chain(&config.temperature),
+            description: chain(&config.description),
+            header: chain(&config.header),
+            graph: chain(&config.graph),
+            tasks: chain(&config.tasks),
+        }
+    }
+}
+
+impl Default for Fonts {
+    fn default() -> Fonts {
+        Fonts::load(&FontConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_font_fallback() {
+        let regular = FontSource::Regular.load();
+        let medium = FontSource::Medium.load();
+        let chain = FontChain::new(regular, vec![medium]);
+
+        assert!(std::ptr::eq(chain.font_for('a').unwrap(), chain.primary()));
+        // Comfortaa doesn't have CJK glyphs, so neither font is used
+        assert!(chain.font_for('雨').is_none());
+
+        let bitmap = FontFace::Bitmap(parse_bdf(include_str!("../tests/bitmap_test.bdf")));
+        let chain = FontChain::new(bitmap, vec![FontSource::Regular.load()]);
+        assert!(std::ptr::eq(chain.font_for('A').unwrap(), chain.primary()));
+        assert!(matches!(chain.font_for('B'), Some(FontFace::TrueType(_))));
+        // scaled up 3x from 7px ascent and 2px descent
+        assert_eq!(chain.v_metrics(Scale::uniform(24.0)).ascent, 21.0);
+
+        let config = FontConfig::from_json(&serde_json::json!({
+            "temperature": {"weight": "medium"},
+            "tasks": {"file": "/usr/share/fonts/noto.ttf"},
+            "fallback": ["/usr/share/fonts/emoji.ttf"],
+        }));
+        assert_eq!(config.temperature, FontSource::Medium);
+        assert_eq!(config.description, FontSource::Regular);
+        assert_eq!(config.tasks, FontSource::File("/usr/share/fonts/noto.ttf".into()));
+        assert_eq!(config.fallback, vec![PathBuf::from("/usr/share/fonts/emoji.ttf")]);
+    }
+}
@@ -0,0 +1,277 @@
+/// The raw gridpoint data, which has layers the forecast endpoints don't, like precipitation
+/// amounts and sky cover. Each layer is a list of values with an ISO 8601 `validTime` interval
+/// like `2023-10-16T18:00:00+00:00/PT6H`, which gets expanded into one value per hour so it lines
+/// up with the hourly forecast.
+
+use std::collections::BTreeMap;
+
+use chrono::prelude::*;
+use chrono::Duration;
+use reqwest::blocking::Client;
+use serde_json::Value;
+
+use crate::{EnvData, ForecastPeriod};
+
+const GRIDPOINT_DATA_URL: &str = "https://api.weather.gov/gridpoints/{office}/{gridpoint}";
+const MM_PER_INCH: f64 = 25.4;
+
+/// Value for each hour, keyed by the start of the hour
+pub type HourlySeries = BTreeMap<DateTime<Utc>, f64>;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GridpointData {
+    /// inches of liquid precipitation in each hour
+    pub precip_in: HourlySeries,
+    /// inches of snow in each hour
+    pub snowfall_in: HourlySeries,
+    /// percentage out of 100
+    pub sky_cover: HourlySeries,
+    pub apparent_temp_f: HourlySeries,
+}
+
+/// How an interval's value is spread over the hours in it
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expansion {
+    /// The value is a total for the interval, like a precipitation amount, so each hour gets an
+    /// equal share of it
+    Split,
+    /// The value holds for the whole interval, like sky cover, so each hour gets all of it
+    Repeat,
+}
+
+/// The gridpoint data only adds extra fields to the forecast, so a failed request returns `None`
+/// instead of taking down the display
+pub fn get_gridpoint_data(env_data: &EnvData, client: &Client) -> Option<String> {
+    let url = GRIDPOINT_DATA_URL.replace("{office}", &env_data.weather_office)
+        .replace("{gridpoint}", &env_data.weather_gridpoint);
+    client.get(url).send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .ok()
+}
+
+/// Parses the layers used by the display. Layers that are missing or in a unit that isn't
+/// recognized come back empty, and values that are null are skipped. Returns `None` if the json
+/// doesn't parse.
+pub fn parse_gridpoint_data(json_str: &str) -> Option<GridpointData> {
+    let data: Value = match serde_json::from_str(json_str) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("skipping gridpoint data that failed to parse: {err}");
+            return None;
+        }
+    };
+    let properties = &data["properties"];
+
+    Some(GridpointData {
+        precip_in: expand_layer(&properties["quantitativePrecipitation"], Expansion::Split, length_to_inches),
+        snowfall_in: expand_layer(&properties["snowfallAmount"], Expansion::Split, length_to_inches),
+        sky_cover: expand_layer(&properties["skyCover"], Expansion::Repeat, |value, _| Some(value)),
+        apparent_temp_f: expand_layer(&properties["apparentTemperature"], Expansion::Repeat, temp_to_f),
+    })
+}
+
+/// Fills in each period's gridpoint fields from the hour it starts in
+pub fn merge_gridpoint_data(periods: &mut [ForecastPeriod], gridpoint: &GridpointData) {
+    for period in periods {
+        let hour = period.start_time.with_timezone(&Utc);
+        period.precip_in = gridpoint.precip_in.get(&hour).map(|p| *p as f32);
+        period.snowfall_in = gridpoint.snowfall_in.get(&hour).map(|s| *s as f32);
+        period.sky_cover = gridpoint.sky_cover.get(&hour).map(|c| c.round() as u64);
+        period.apparent_temp_f = gridpoint.apparent_temp_f.get(&hour).map(|t| t.round() as i32);
+    }
+}
+
+/// Expands a layer's intervals into hourly values, converting each one with `convert(value, uom)`.
+/// If `convert` doesn't know the unit the whole layer comes back empty.
+fn expand_layer(layer: &Value, expansion: Expansion, convert: fn(f64, &str) -> Option<f64>) -> HourlySeries {
+    let uom = layer["uom"].as_str().unwrap_or("");
+    let mut series = HourlySeries::new();
+    let values = match layer["values"].as_array() {
+        Some(values) => values,
+        None => return series,
+    };
+
+    for item in values {
+        let value = match item["value"].as_f64() {
+            Some(value) => value,
+            None => continue,
+        };
+        let value = match convert(value, uom) {
+            Some(value) => value,
+            None => {
+                eprintln!("skipping gridpoint layer with unexpected unit {uom:?}");
+                return HourlySeries::new();
+            }
+        };
+        let valid_time = item["validTime"].as_str().unwrap_or("");
+        let (start, duration) = match parse_valid_time(valid_time) {
+            Some(interval) => interval,
+            None => {
+                eprintln!("skipping gridpoint value with invalid validTime {valid_time:?}");
+                continue;
+            }
+        };
+
+        let hours = duration.num_hours().max(1);
+        let hourly_value = match expansion {
+            Expansion::Split => value / hours as f64,
+            Expansion::Repeat => value,
+        };
+        for hour in 0..hours {
+            series.insert(start + Duration::hours(hour), hourly_value);
+        }
+    }
+
+    series
+}
+
+/// Parses an interval like `2023-10-16T18:00:00+00:00/PT6H` into its start and duration
+fn parse_valid_time(valid_time: &str) -> Option<(DateTime<Utc>, Duration)> {
+    let (start, duration) = valid_time.split_once('/')?;
+    let start = DateTime::parse_from_rfc3339(start).ok()?.with_timezone(&Utc);
+    Some((start, parse_iso_duration(duration)?))
+}
+
+/// Parses an ISO 8601 duration like `P1DT6H`. Years and months aren't supported since they don't
+/// have a fixed length, and the gridpoint data doesn't use them.
+fn parse_iso_duration(duration: &str) -> Option<Duration> {
+    let rest = duration.strip_prefix('P')?;
+    let (date_part, time_part) = match rest.split_once('T') {
+        Some((date_part, time_part)) => (date_part, Some(time_part)),
+        None => (rest, None),
+    };
+
+    let mut total = Duration::zero();
+    let mut parse_part = |part: &str, units: &[(char, i64)]| -> Option<()> {
+        let mut number = String::new();
+        for c in part.chars() {
+            if c.is_ascii_digit() {
+                number.push(c);
+                continue;
+            }
+            let (_, seconds) = units.iter().find(|(unit, _)| *unit == c)?;
+            total = total + Duration::seconds(number.parse::<i64>().ok()? * seconds);
+            number.clear();
+        }
+        // a trailing number without a unit isn't valid
+        number.is_empty().then_some(())
+    };
+
+    parse_part(date_part, &[('W', 7 * 86400), ('D', 86400)])?;
+    if let Some(time_part) = time_part {
+        if time_part.is_empty() {
+            return None;
+        }
+        parse_part(time_part, &[('H', 3600), ('M', 60), ('S', 1)])?;
+    }
+
+    Some(total)
+}
+
+fn length_to_inches(value: f64, uom: &str) -> Option<f64> {
+    match uom {
+        "wmoUnit:mm" => Some(value / MM_PER_INCH),
+        "wmoUnit:cm" => Some(value * 10.0 / MM_PER_INCH),
+        "wmoUnit:m" => Some(value * 1000.0 / MM_PER_INCH),
+        _ => None,
+    }
+}
+
+fn temp_to_f(value: f64, uom: &str) -> Option<f64> {
+    match uom {
+        "wmoUnit:degC" => Some(value * 1.8 + 32.0),
+        "wmoUnit:degF" => Some(value),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_iso_duration() {
+        assert_eq!(parse_iso_duration("PT1H"), Some(Duration::hours(1)));
+        assert_eq!(parse_iso_duration("PT6H"), Some(Duration::hours(6)));
+        assert_eq!(parse_iso_duration("P1D"), Some(Duration::days(1)));
+        assert_eq!(parse_iso_duration("P2DT12H"), Some(Duration::hours(60)));
+        assert_eq!(parse_iso_duration("P1W"), Some(Duration::days(7)));
+        assert_eq!(parse_iso_duration("PT1H30M"), Some(Duration::minutes(90)));
+        assert_eq!(parse_iso_duration("PT"), None);
+        assert_eq!(parse_iso_duration("P1Y"), None);
+        assert_eq!(parse_iso_duration("PT6"), None);
+        assert_eq!(parse_iso_duration("6H"), None);
+    }
+
+    #[test]
+    fn test_parse_gridpoint_data() {
+        let data = json!({
+            "properties": {
+                "quantitativePrecipitation": {
+                    "uom": "wmoUnit:mm",
+                    "values": [
+                        { "validTime": "2023-10-16T18:00:00+00:00/PT6H", "value": 15.24 },
+                        { "validTime": "2023-10-17T00:00:00+00:00/PT6H", "value": null },
+                    ],
+                },
+                "skyCover": {
+                    "uom": "wmoUnit:percent",
+                    "values": [
+                        { "validTime": "2023-10-16T18:00:00+00:00/PT2H", "value": 40 },
+                        { "validTime": "2023-10-16T20:00:00+00:00/PT1H", "value": 75 },
+                    ],
+                },
+                "apparentTemperature": {
+                    "uom": "wmoUnit:degC",
+                    "values": [
+                        { "validTime": "2023-10-16T18:00:00+00:00/P1DT1H", "value": 10 },
+                    ],
+                },
+                "snowfallAmount": {
+                    "uom": "wmoUnit:in",
+                    "values": [
+                        { "validTime": "2023-10-16T18:00:00+00:00/PT6H", "value": 1 },
+                    ],
+                },
+            },
+        });
+        let gridpoint = parse_gridpoint_data(&data.to_string()).unwrap();
+
+        let hour = |h: i64| Utc.with_ymd_and_hms(2023, 10, 16, 18, 0, 0).unwrap() + Duration::hours(h);
+        // 0.6 inches split over 6 hours
+        assert_eq!(gridpoint.precip_in.len(), 6);
+        assert!(gridpoint.precip_in.values().all(|p| (p - 0.1).abs() < 1e-9));
+        assert!(!gridpoint.precip_in.contains_key(&hour(6)));
+        // an unknown unit leaves the layer empty
+        assert!(gridpoint.snowfall_in.is_empty());
+        assert_eq!(gridpoint.sky_cover.values().copied().collect::<Vec<f64>>(), vec![40.0, 40.0, 75.0]);
+        assert_eq!(gridpoint.apparent_temp_f.len(), 25);
+        assert_eq!(gridpoint.apparent_temp_f.get(&hour(24)), Some(&50.0));
+
+        let mut periods = vec![ForecastPeriod {
+            period_name: None,
+            start_time: hour(1).into(),
+            end_time: hour(2).into(),
+            temp_f: 52,
+            rain_prob: 60,
+            wind_speed: 5,
+            wind_direction: None,
+            dewpoint_f: None,
+            short_desc: "Rain".into(),
+            long_desc: None,
+            precip_in: None,
+            snowfall_in: None,
+            sky_cover: None,
+            apparent_temp_f: None,
+        }];
+        merge_gridpoint_data(&mut periods, &gridpoint);
+        assert_eq!(periods[0].precip_in, Some(0.1));
+        assert_eq!(periods[0].sky_cover, Some(40));
+        assert_eq!(periods[0].apparent_temp_f, Some(50));
+        assert_eq!(periods[0].snowfall_in, None);
+
+        assert_eq!(parse_gridpoint_data("<html>502 Bad Gateway</html>"), None);
+    }
+}
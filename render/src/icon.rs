@@ -0,0 +1,212 @@
+/// Small weather icons drawn from simple shapes, so they look sharp at any size on the e-ink display
+
+use image::RgbImage;
+use imageproc::drawing::{draw_filled_circle_mut, draw_filled_rect_mut, draw_line_segment_mut, draw_polygon_mut};
+use imageproc::point::Point;
+use imageproc::rect::Rect;
+
+use crate::{ForecastPoint, PrecipType};
+
+/// Sky cover percentages below which it counts as clear and partly cloudy
+const CLEAR_SKY_COVER: u64 = 25;
+const PARTLY_CLOUDY_SKY_COVER: u64 = 70;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeatherIcon {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Rain,
+    Snow,
+    /// Sleet, freezing rain, or rain and snow together
+    Mixed,
+    Thunderstorm,
+    Fog,
+}
+
+impl WeatherIcon {
+    /// Picks the icon for a forecast point from its description, falling back on the sky cover for
+    /// how cloudy it is
+    pub fn for_point(point: &ForecastPoint) -> WeatherIcon {
+        let desc = point.short_desc.as_deref().unwrap_or("").to_lowercase();
+        let has_any = |words: &[&str]| words.iter().any(|w| desc.contains(w));
+
+        if has_any(&["thunder", "t-storm"]) {
+            return WeatherIcon::Thunderstorm;
+        }
+        match point.precip_type() {
+            Some(PrecipType::Rain) => return WeatherIcon::Rain,
+            Some(PrecipType::Snow) => return WeatherIcon::Snow,
+            Some(PrecipType::Mixed) => return WeatherIcon::Mixed,
+            None => {}
+        }
+        if has_any(&["fog", "haze", "smoke", "mist"]) {
+            return WeatherIcon::Fog;
+        }
+
+        match point.sky_cover {
+            Some(cover) if cover < CLEAR_SKY_COVER => WeatherIcon::Clear,
+            Some(cover) if cover < PARTLY_CLOUDY_SKY_COVER => WeatherIcon::PartlyCloudy,
+            Some(_) => WeatherIcon::Cloudy,
+            None if has_any(&["partly", "mostly sunny", "mostly clear"]) => WeatherIcon::PartlyCloudy,
+            None if has_any(&["cloudy", "overcast"]) => WeatherIcon::Cloudy,
+            None => WeatherIcon::Clear,
+        }
+    }
+}
+
+/// Draws `icon` into a square `size` pixels across with its top left corner at `x`, `y`. At
+/// night the sun is swapped for the moon.
+pub fn draw_weather_icon(image: &mut RgbImage, icon: WeatherIcon, night: bool, x: i32, y: i32, size: i32) {
+    let black = image::Rgb([0u8, 0u8, 0u8]);
+    let red = image::Rgb([255u8, 0u8, 0u8]);
+    let s = size as f32;
+    // everything is laid out on a 0 to 1 grid and scaled up to the size
+    let at = |fx: f32, fy: f32| (x as f32 + fx*s, y as f32 + fy*s);
+
+    match icon {
+        WeatherIcon::Clear => draw_sky_body(image, night, at(0.5, 0.5), s * 0.22),
+        WeatherIcon::PartlyCloudy => {
+            draw_sky_body(image, night, at(0.35, 0.35), s * 0.17);
+            draw_cloud(image, at(0.58, 0.68), s * 0.62);
+        }
+        WeatherIcon::Cloudy => draw_cloud(image, at(0.5, 0.55), s * 0.9),
+        WeatherIcon::Fog => {
+            for fy in [0.35, 0.5, 0.65] {
+                for offset in [0.0, 1.0] {
+                    draw_line_segment_mut(image, at(0.15, fy + offset/s), at(0.85, fy + offset/s), black);
+                }
+            }
+        }
+        WeatherIcon::Rain | WeatherIcon::Snow | WeatherIcon::Mixed | WeatherIcon::Thunderstorm => {
+            draw_cloud(image, at(0.5, 0.4), s * 0.9);
+            for (i, fx) in [0.3, 0.5, 0.7].into_iter().enumerate() {
+                let drop = match icon {
+                    WeatherIcon::Mixed => if i % 2 == 0 { WeatherIcon::Rain } else { WeatherIcon::Snow },
+                    _ => icon,
+                };
+                match drop {
+                    WeatherIcon::Snow => {
+                        let (cx, cy) = at(fx, 0.8);
+                        draw_filled_circle_mut(image, (cx as i32, cy as i32), (s * 0.05).max(1.0) as i32, black);
+                    }
+                    WeatherIcon::Thunderstorm => {}
+                    _ => {
+                        for offset in [0.0, 1.0] {
+                            draw_line_segment_mut(image, at(fx + 0.05 + offset/s, 0.7), at(fx - 0.05 + offset/s, 0.92), black);
+                        }
+                    }
+                }
+            }
+            if icon == WeatherIcon::Thunderstorm {
+                let bolt: Vec<Point<i32>> = [(0.55, 0.55), (0.4, 0.8), (0.5, 0.8), (0.42, 1.0), (0.65, 0.72), (0.54, 0.72), (0.62, 0.55)]
+                    .iter()
+                    .map(|(fx, fy)| {
+                        let (px, py) = at(*fx, *fy);
+                        Point::new(px as i32, (py as i32).min(y + size - 1))
+                    })
+                    .collect();
+                draw_polygon_mut(image, &bolt, red);
+            }
+        }
+    }
+}
+
+/// Draws the sun in red with rays, or the moon as a black crescent, centered on `center`
+fn draw_sky_body(image: &mut RgbImage, night: bool, center: (f32, f32), radius: f32) {
+    let black = image::Rgb([0u8, 0u8, 0u8]);
+    let white = image::Rgb([255u8, 255u8, 255u8]);
+    let red = image::Rgb([255u8, 0u8, 0u8]);
+    let (cx, cy) = center;
+
+    if night {
+        draw_filled_circle_mut(image, (cx as i32, cy as i32), radius as i32, black);
+        let bite = (cx + radius * 0.5, cy - radius * 0.35);
+        draw_filled_circle_mut(image, (bite.0 as i32, bite.1 as i32), (radius * 0.85) as i32, white);
+        return;
+    }
+
+    draw_filled_circle_mut(image, (cx as i32, cy as i32), radius as i32, red);
+    for i in 0..8 {
+        let angle = i as f32 * std::f32::consts::FRAC_PI_4;
+        let (dx, dy) = (angle.cos(), angle.sin());
+        let start = (cx + dx * radius * 1.35, cy + dy * radius * 1.35);
+        let end = (cx + dx * radius * 1.9, cy + dy * radius * 1.9);
+        draw_line_segment_mut(image, start, end, red);
+    }
+}
+
+/// Draws an outlined cloud `width` pixels wide centered on `center`, covering anything behind it
+fn draw_cloud(image: &mut RgbImage, center: (f32, f32), width: f32) {
+    let black = image::Rgb([0u8, 0u8, 0u8]);
+    let white = image::Rgb([255u8, 255u8, 255u8]);
+    let (cx, cy) = center;
+    let unit = width / 4.0;
+
+    // three bumps on top of a flat bottom, drawn in black and then again in white a bit smaller
+    // to leave an outline
+    let bumps = [(-1.0, 0.3, 0.75), (0.1, -0.15, 1.05), (1.1, 0.35, 0.65)];
+    for (color, inset) in [(black, 0.0), (white, 1.5)] {
+        for (bx, by, r) in bumps {
+            let radius = (r * unit - inset).max(1.0);
+            draw_filled_circle_mut(image, ((cx + bx*unit) as i32, (cy + by*unit) as i32), radius as i32, color);
+        }
+        let left = cx - 1.0*unit;
+        let right = cx + 1.1*unit;
+        let bottom = cy + 0.3*unit + 0.65*unit - inset;
+        let top = cy + 0.3*unit;
+        if right > left && bottom > top {
+            draw_filled_rect_mut(image, Rect::at(left as i32, top as i32)
+                .of_size((right - left) as u32, (bottom - top).max(1.0) as u32), color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+    use image::Rgb;
+
+    /// Every icon in a row, by day on top and by night underneath, 40 pixels across with a 5
+    /// pixel margin
+    fn draw_icon_sheet() -> RgbImage {
+        let icons = [WeatherIcon::Clear, WeatherIcon::PartlyCloudy, WeatherIcon::Cloudy, WeatherIcon::Rain,
+            WeatherIcon::Snow, WeatherIcon::Mixed, WeatherIcon::Thunderstorm, WeatherIcon::Fog];
+        let mut image = RgbImage::from_pixel(8 * 50, 100, Rgb([255u8, 255u8, 255u8]));
+        for (i, icon) in icons.into_iter().enumerate() {
+            draw_weather_icon(&mut image, icon, false, i as i32 * 50 + 5, 5, 40);
+            draw_weather_icon(&mut image, icon, true, i as i32 * 50 + 5, 55, 40);
+        }
+        image
+    }
+
+    #[test]
+    fn test_weather_icon() {
+        let point = |desc: &str, sky_cover: Option<u64>| ForecastPoint {
+            short_desc: Some(desc.into()),
+            sky_cover,
+            ..ForecastPoint::new(DateTime::parse_from_rfc3339("2023-10-16T20:00:00-04:00").unwrap(), 55, 20)
+        };
+        assert_eq!(WeatherIcon::for_point(&point("Sunny", None)), WeatherIcon::Clear);
+        assert_eq!(WeatherIcon::for_point(&point("Partly Cloudy", None)), WeatherIcon::PartlyCloudy);
+        assert_eq!(WeatherIcon::for_point(&point("Mostly Cloudy", None)), WeatherIcon::Cloudy);
+        assert_eq!(WeatherIcon::for_point(&point("Mostly Cloudy", Some(40))), WeatherIcon::PartlyCloudy);
+        assert_eq!(WeatherIcon::for_point(&point("Chance Rain Showers", None)), WeatherIcon::Rain);
+        assert_eq!(WeatherIcon::for_point(&point("Chance Showers And Thunderstorms", None)), WeatherIcon::Thunderstorm);
+        assert_eq!(WeatherIcon::for_point(&point("Patchy Fog", Some(90))), WeatherIcon::Fog);
+        assert_eq!(WeatherIcon::for_point(&point("Patchy Freezing Fog", None)), WeatherIcon::Fog);
+        assert_eq!(WeatherIcon::for_point(&point("Patchy Ice Fog", None)), WeatherIcon::Fog);
+        assert_eq!(WeatherIcon::for_point(&point("Light Snow", None)), WeatherIcon::Snow);
+
+        // every icon stays inside its square
+        let sheet = draw_icon_sheet();
+        let white = Rgb([255u8, 255u8, 255u8]);
+        for (x, y, p) in sheet.enumerate_pixels() {
+            let (fx, fy) = (x % 50, y % 50);
+            if *p != white {
+                assert!((5..45).contains(&fx) && (5..45).contains(&fy), "pixel outside of icon at {x} {y}");
+            }
+        }
+    }
+}
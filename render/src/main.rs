@@ -17,11 +17,44 @@ pub use env_data::*;
 mod weather;
 pub use weather::*;
 
+mod gridpoint;
+pub use gridpoint::*;
+
 mod tasks;
 pub use tasks::*;
 
+mod picture;
+pub use picture::*;
+
 pub(crate) mod text;
 
+mod bitmap_font;
+pub use bitmap_font::*;
+
+mod fonts;
+pub use fonts::*;
+
+mod sun;
+pub use sun::*;
+
+mod moon;
+pub use moon::*;
+
+mod archive;
+pub use archive::*;
+
+mod accuracy;
+pub use accuracy::*;
+
+mod pattern;
+pub use pattern::*;
+
+mod chart;
+pub use chart::*;
+
+mod icon;
+pub use icon::*;
+
 pub mod draw;
 pub use draw::*;
 
@@ -47,12 +80,26 @@ pub fn get_output_path() -> PathBuf {
 
 pub struct DisplayData {
     current_weather: CurrentWeather,
-    forecast: Forecast5Day,
+    forecast: ForecastWindow,
+    /// Every hour of the forecast that was fetched, before it was cut down to `forecast`
+    hourly_forecast: FullForecast,
+    /// The named periods from the daily forecast, like "Tonight" and "Tuesday"
+    daily_forecast: Vec<ForecastPeriod>,
+    /// Observed temperatures from the last day, oldest first
+    observations: Vec<Observation>,
     todoist_tasks: Vec<Task>,
+    picture: Option<image::DynamicImage>,
 }
 
-/// Returns (current_weather_json, hourly_weather_json, tasks_json)
-fn gather_data(env_data: &EnvData) -> (String, String, String) {
+/// (observations_json, hourly_weather_json, daily_weather_json, gridpoint_json, tasks_json, picture_bytes).
+/// The daily forecast, gridpoint data and picture are optional, so their requests are allowed to fail.
+type GatheredData = (String, String, Option<String>, Option<String>, String, Option<Vec<u8>>);
+
+/// Returns the responses from all of the requests, see `GatheredData`
+///
+/// `local_picture` is passed through as-is since local files have to be read before the sandbox
+/// is applied.
+fn gather_data(env_data: &EnvData, local_picture: Option<Vec<u8>>) -> GatheredData {
     extrasafe::SafetyContext::new()
         .enable(
             extrasafe::builtins::SystemIO::nothing()
@@ -74,19 +121,24 @@ fn gather_data(env_data: &EnvData) -> (String, String, String) {
         .apply_to_current_thread()
         .unwrap();
     let client = create_weather_client(&env_data);
-    //let daily_forecast = get_daily_forecast(&env_data, &client);
-    //println!("{daily_forecast:#?}");
 
     let todoist_client = create_todoist_client(&env_data);
     let tasks_json = get_tasks(&todoist_client);
 
-    let current_weather_json = get_current_weather(&env_data, &client);
+    let observations_json = get_observations(&env_data, &client);
     let hourly_forecast_json = get_hourly_forecast(&env_data, &client);
+    let daily_forecast_json = get_daily_forecast(env_data, &client);
+    let gridpoint_json = get_gridpoint_data(&env_data, &client);
+
+    let picture_bytes = match &env_data.picture_source {
+        Some(PictureSource::Url(url)) => get_picture(url, &client),
+        _ => local_picture,
+    };
 
-    (current_weather_json, hourly_forecast_json, tasks_json)
+    (observations_json, hourly_forecast_json, daily_forecast_json, gridpoint_json, tasks_json, picture_bytes)
 }
 
-fn parse_data(current_weather_json: String, hourly_forecast_json: String, tasks_json: String) -> DisplayData {
+fn parse_data(observations_json: String, hourly_forecast_json: String, daily_forecast_json: Option<String>, gridpoint_json: Option<String>, tasks_json: String, picture_bytes: Option<Vec<u8>>, forecast_options: &ForecastOptions) -> DisplayData {
     // start a new context for parsing the json
     extrasafe::SafetyContext::new()
         .enable(
@@ -97,20 +149,73 @@ fn parse_data(current_weather_json: String, hourly_forecast_json: String, tasks_
         .apply_to_current_thread()
         .unwrap();
     let todoist_tasks = parse_tasks(&tasks_json);
-    let current_weather = parse_current_weather(&current_weather_json);
-    let full_forecast = parse_hourly_forecast(&hourly_forecast_json);
-    let forecast = Forecast5Day::new(&full_forecast);
+    let current_weather = parse_current_weather(&observations_json);
+    let observations = parse_observation_history(&observations_json);
+    let mut full_forecast = parse_hourly_forecast(&hourly_forecast_json);
+    if let Some(gridpoint) = gridpoint_json.and_then(|json| parse_gridpoint_data(&json)) {
+        merge_gridpoint_data(&mut full_forecast, &gridpoint);
+    }
+    let forecast = ForecastWindow::new(&full_forecast, forecast_options);
+    let hourly_forecast = full_forecast.iter().map(ForecastPoint::from).collect();
+    let daily_forecast = daily_forecast_json.map(|json| parse_daily_forecast(&json)).unwrap_or_default();
+    let picture = picture_bytes.and_then(|bytes| parse_picture(&bytes));
 
     DisplayData {
         current_weather,
         forecast,
+        hourly_forecast,
+        daily_forecast,
+        observations,
         todoist_tasks,
+        picture,
     }
 }
 
+/// Writes a csv and png to `<output filename>.csv` and `<output filename>.png` comparing the
+/// archived forecasts to the archived observations
+fn accuracy_report() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.len() != 4 {
+        eprintln!("usage: halldisplay accuracy <env json filename> <output filename>");
+        return;
+    }
+    let env_data = EnvData::from_file(&PathBuf::from(&args[2]));
+    let output_filepath = PathBuf::from(&args[3]);
+    let archive = env_data.archive.as_ref()
+        .expect("the accuracy report needs an archive section in the env data");
+
+    // everything is read before the sandbox is applied, same as the display
+    let records = read_archive(archive);
+    let fonts = Fonts::load(&env_data.fonts);
+    let mut csv_file = File::create(output_filepath.with_extension("csv")).expect("failed to create file");
+    let mut image_file = File::create(output_filepath.with_extension("png")).expect("failed to create file");
+
+    extrasafe::SafetyContext::new()
+        .enable(
+            extrasafe::builtins::SystemIO::nothing()
+                .allow_stdout()
+                .allow_stderr()
+                .allow_file_write(&csv_file)
+                .allow_file_write(&image_file)
+                .allow_close()
+            ).unwrap()
+        .apply_to_current_thread()
+        .unwrap();
+
+    let stats = compute_accuracy(&records);
+    println!("csv file {:?}", csv_file.write_all(accuracy_csv(&stats).as_bytes()));
+    let image = draw_accuracy_report(&stats, &records, &fonts.header);
+    println!("image file {:?}", image.write_to(&mut image_file, image::ImageOutputFormat::Png));
+}
+
 fn main() {
+    if std::env::args().nth(1).as_deref() == Some("accuracy") {
+        accuracy_report();
+        return;
+    }
     if std::env::args().len() != 3 {
         eprintln!("usage: halldisplay <env json filename> <output filename>");
+        eprintln!("       halldisplay accuracy <env json filename> <output filename>");
         return;
     }
     let env_data = get_env_data();
@@ -128,19 +233,37 @@ fn main() {
 
     let display_data: DisplayData;
 
+    // like the env data, font files are read before the sandbox is applied
+    let fonts = Fonts::load(&env_data.fonts);
+
+    // local pictures have to be read now, before any of the sandboxes are applied
+    let today = chrono::Utc::now().with_timezone(&env_data.local_timezone).date_naive();
+    let local_picture = match &env_data.picture_source {
+        Some(PictureSource::Directory(dir)) => read_local_picture(dir, today),
+        _ => None,
+    };
+
+    // the archive file is opened now, and afterwards only writing to it is allowed
+    let mut archive_file = env_data.archive.as_ref().map(|archive| {
+        prune_archive(archive, today);
+        open_archive_file(archive, today)
+    });
+
     let use_debug_data = false;
     if !use_debug_data {
+        let forecast_options = env_data.forecast.clone();
         let env_data = env_data.clone();
         thread::spawn(move || {
             parse_start_rx.recv().unwrap();
-            let data = gather_data(&env_data);
+            let data = gather_data(&env_data, local_picture);
             json_sender.send(data).unwrap();
         });
 
         thread::spawn(move || {
-            let (current_weather_json, hourly_weather_json, tasks_json) = json_receiver.recv()
+            let (observations_json, hourly_weather_json, daily_weather_json, gridpoint_json, tasks_json, picture_bytes) = json_receiver.recv()
                 .expect("failed to get json");
-            let display_data = parse_data(current_weather_json, hourly_weather_json, tasks_json);
+            let display_data = parse_data(observations_json, hourly_weather_json, daily_weather_json, gridpoint_json, tasks_json,
+                picture_bytes, &forecast_options);
             data_sender.send(display_data).unwrap();
         });
 
@@ -149,15 +272,17 @@ fn main() {
         data_sender.send(get_test_data()).unwrap();
     }
 
+    let mut system_io = extrasafe::builtins::SystemIO::nothing()
+        .allow_stdout()
+        .allow_stderr()
+        .allow_file_write(&output_data_file)
+        .allow_file_write(&output_image_file)
+        .allow_close();
+    if let Some(archive_file) = &archive_file {
+        system_io = system_io.allow_file_write(archive_file);
+    }
     extrasafe::SafetyContext::new()
-        .enable(
-            extrasafe::builtins::SystemIO::nothing()
-                .allow_stdout()
-                .allow_stderr()
-                .allow_file_write(&output_data_file)
-                .allow_file_write(&output_image_file)
-                .allow_close()
-            ).unwrap()
+        .enable(system_io).unwrap()
         .apply_to_current_thread()
         .unwrap();
     parse_start.send(()).expect("failed to start json thread");
@@ -165,7 +290,16 @@ fn main() {
             .expect("failed to get data");
 
     let current_time = chrono::Utc::now().with_timezone(&env_data.local_timezone);
-    let (buffer, image) = render(current_time, display_data);
+    let archive_record = ArchiveRecord {
+        fetched: current_time.fixed_offset(),
+        current_weather: display_data.current_weather.clone(),
+        forecast: display_data.hourly_forecast.clone(),
+    };
+    let (buffer, image) = render(current_time, display_data, &fonts, &env_data.render_options);
+
+    if let Some(archive_file) = &mut archive_file {
+        append_archive_record(archive_file, &archive_record);
+    }
 
     println!("image file {:?}", image.write_to(&mut output_image_file, image::ImageOutputFormat::Png));
 
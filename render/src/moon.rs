@@ -0,0 +1,83 @@
+/// Moon phase calculated locally from the time since a known new moon
+
+use chrono::prelude::*;
+
+/// Average length of a lunar cycle in days
+const SYNODIC_MONTH: f64 = 29.530588853;
+/// Unix timestamp of the new moon on 2000-01-06 18:14 UTC
+const KNOWN_NEW_MOON: i64 = 947182440;
+
+const PHASE_NAMES: [&str; 8] = [
+    "New Moon",
+    "Waxing Crescent",
+    "First Quarter",
+    "Waxing Gibbous",
+    "Full Moon",
+    "Waning Gibbous",
+    "Last Quarter",
+    "Waning Crescent",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MoonPhase {
+    /// Days since the last new moon
+    pub age_days: f64,
+    /// Fraction of the moon's disc that is lit, from 0 to 1
+    pub illumination: f64,
+}
+
+impl MoonPhase {
+    /// How far through the cycle the moon is, from 0 at the new moon to 0.5 at the full moon and
+    /// back to 1
+    pub fn cycle_fraction(&self) -> f64 {
+        self.age_days / SYNODIC_MONTH
+    }
+
+    pub fn is_waxing(&self) -> bool {
+        self.cycle_fraction() < 0.5
+    }
+
+    pub fn name(&self) -> &str {
+        let idx = (self.cycle_fraction() * 8.0).round() as usize % PHASE_NAMES.len();
+        PHASE_NAMES[idx]
+    }
+}
+
+/// Calculates the moon phase at `time`. This uses the average length of a cycle, so it can be off
+/// by several hours, which is plenty for a daily display.
+pub fn moon_phase<Tz: TimeZone>(time: &DateTime<Tz>) -> MoonPhase {
+    let days_since_new_moon = (time.timestamp() - KNOWN_NEW_MOON) as f64 / 86400.0;
+    let age_days = days_since_new_moon.rem_euclid(SYNODIC_MONTH);
+    let angle = age_days / SYNODIC_MONTH * std::f64::consts::TAU;
+
+    MoonPhase {
+        age_days,
+        illumination: (1.0 - angle.cos()) / 2.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moon_phase() {
+        let at = |s: &str| moon_phase(&DateTime::parse_from_rfc3339(s).unwrap());
+
+        // the annular eclipse on 2023-10-14 was at the new moon
+        let new_moon = at("2023-10-14T17:55:00Z");
+        assert_eq!(new_moon.name(), "New Moon");
+        assert!(new_moon.illumination < 0.02);
+
+        let first_quarter = at("2023-10-22T03:29:00Z");
+        assert_eq!(first_quarter.name(), "First Quarter");
+        assert!(first_quarter.is_waxing());
+
+        let full_moon = at("2023-10-28T20:24:00Z");
+        assert_eq!(full_moon.name(), "Full Moon");
+        assert!(full_moon.illumination > 0.98);
+
+        assert_eq!(at("2023-11-01T12:00:00Z").name(), "Waning Gibbous");
+        assert!(!at("2023-11-01T12:00:00Z").is_waxing());
+    }
+}
@@ -0,0 +1,202 @@
+/// Pattern fills for shading areas on the display. The panel can only show white, black and red,
+/// so areas are told apart by the pattern they're shaded with instead of by a color or gray level.
+///
+/// The patterns are anchored to the image rather than to the shape being filled, so neighbouring
+/// areas with the same fill line up with each other.
+
+use image::{Rgb, RgbImage};
+
+/// The shape of a pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    /// Short dashes slanting down to the right
+    Diagonal,
+    /// Lines slanting both ways
+    Crosshatch,
+    /// Staggered dots
+    Dots,
+    // the forecast graph doesn't need this yet, but it's here for charts with more series
+    #[allow(dead_code)]
+    Horizontal,
+    Vertical,
+}
+
+/// How closely a pattern is spaced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Density {
+    Sparse,
+    #[default]
+    Normal,
+    Dense,
+}
+
+impl Density {
+    /// Spacing in pixels for the line patterns
+    fn line_period(&self) -> u32 {
+        match self {
+            Density::Sparse => 8,
+            Density::Normal => 6,
+            Density::Dense => 4,
+        }
+    }
+
+    /// Horizontal spacing in pixels between the dots on a row
+    fn dot_period(&self) -> u32 {
+        match self {
+            Density::Sparse => 8,
+            Density::Normal => 4,
+            Density::Dense => 2,
+        }
+    }
+}
+
+/// Rows of dots are always this far apart, with every other row shifted by half the dot spacing
+const DOT_ROW_PERIOD: u32 = 4;
+
+/// A pattern drawn in a single color of ink. Pixels that aren't inked are left as they are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fill {
+    pub pattern: Pattern,
+    pub density: Density,
+    pub color: Rgb<u8>,
+}
+
+impl Fill {
+    pub fn new(pattern: Pattern, color: Rgb<u8>) -> Fill {
+        Fill {
+            pattern,
+            density: Density::default(),
+            color,
+        }
+    }
+
+    /// Whether the pixel at `x`, `y` gets ink
+    pub fn inked(&self, x: u32, y: u32) -> bool {
+        let n = self.density.line_period();
+        match self.pattern {
+            Pattern::Diagonal => {
+                // using some math that took me a bit to get correct, dashes half a period long
+                // that shift over by two pixels every row of dashes
+                let ym = y%n;
+                let yd = y/n;
+                let xm = (x+2*yd)%n;
+                let xcond = (xm == ym) || (xm == ym+1);
+                let ycond = ym < n/2;
+                xcond && ycond
+            }
+            Pattern::Crosshatch => (x + y) % n == 0 || (x + n - y % n) % n == 0,
+            Pattern::Dots => {
+                let p = self.density.dot_period();
+                let offset = (y / DOT_ROW_PERIOD % 2) * p / 2;
+                y % DOT_ROW_PERIOD == 0 && (x + offset) % p == 0
+            }
+            Pattern::Horizontal => y % n == 0,
+            Pattern::Vertical => x % n == 0,
+        }
+    }
+
+    /// Fills the rectangle including both corners. Parts outside the image are skipped.
+    pub fn fill_rect(&self, image: &mut RgbImage, left: u32, top: u32, right: u32, bottom: u32) {
+        let right = right.min(image.width().saturating_sub(1));
+        let bottom = bottom.min(image.height().saturating_sub(1));
+        for y in top..=bottom {
+            for x in left..=right {
+                if self.inked(x, y) {
+                    image.put_pixel(x, y, self.color);
+                }
+            }
+        }
+    }
+
+    /// Fills each column `x` from `tops[x]` down to the bottom of the image. Columns that are None
+    /// are skipped.
+    pub fn fill_under_curve(&self, image: &mut RgbImage, tops: &[Option<u32>]) {
+        let bottom = image.height().saturating_sub(1);
+        for (x, top) in tops.iter().enumerate().take(image.width() as usize) {
+            if let Some(top) = top {
+                self.fill_rect(image, x as u32, *top, x as u32, bottom);
+            }
+        }
+    }
+
+    /// Fills the inside of the polygon with corners at `points`, using the even-odd rule. A pixel
+    /// is inside if its center is.
+    // the forecast graph only fills under curves so far, this is for any other shapes
+    #[allow(dead_code)]
+    pub fn fill_polygon(&self, image: &mut RgbImage, points: &[(f32, f32)]) {
+        if points.len() < 3 {
+            return;
+        }
+        for y in 0..image.height() {
+            let cy = y as f32 + 0.5;
+            // where each edge crosses the middle of the row
+            let mut crossings: Vec<f32> = points.iter()
+                .zip(points.iter().cycle().skip(1))
+                .filter(|((_, y1), (_, y2))| (*y1 <= cy) != (*y2 <= cy))
+                .map(|((x1, y1), (x2, y2))| x1 + (cy - y1) / (y2 - y1) * (x2 - x1))
+                .collect();
+            crossings.sort_by(f32::total_cmp);
+
+            for span in crossings.chunks_exact(2) {
+                // pixels whose centers are between the crossings
+                let left = (span[0] - 0.5).ceil().max(0.0) as u32;
+                let right = (span[1] - 0.5).floor();
+                if right < left as f32 {
+                    continue;
+                }
+                self.fill_rect(image, left, y, right as u32, y);
+            }
+        }
+    }
+
+    /// Draws a square `size` pixels across with an outline, for showing the fill in a legend
+    pub fn draw_swatch(&self, image: &mut RgbImage, x: u32, y: u32, size: u32, outline: Rgb<u8>) {
+        self.fill_rect(image, x, y, x + size - 1, y + size - 1);
+        for i in 0..size {
+            for (px, py) in [(x + i, y), (x + i, y + size - 1), (x, y + i), (x + size - 1, y + i)] {
+                if px < image.width() && py < image.height() {
+                    image.put_pixel(px, py, outline);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_inked(fill: &Fill) -> usize {
+        let mut image = RgbImage::from_pixel(48, 48, Rgb([255u8, 255u8, 255u8]));
+        fill.fill_rect(&mut image, 0, 0, 47, 47);
+        image.pixels().filter(|p| **p == fill.color).count()
+    }
+
+    #[test]
+    fn test_density() {
+        let red = Rgb([255u8, 0u8, 0u8]);
+        let patterns = [Pattern::Diagonal, Pattern::Crosshatch, Pattern::Dots, Pattern::Horizontal, Pattern::Vertical];
+        for pattern in patterns {
+            let count = |density| count_inked(&Fill { density, ..Fill::new(pattern, red) });
+            let (sparse, normal, dense) = (count(Density::Sparse), count(Density::Normal), count(Density::Dense));
+            assert!(0 < sparse && sparse < normal && normal < dense, "{pattern:?}: {sparse} {normal} {dense}");
+        }
+    }
+
+    #[test]
+    fn test_fill_polygon() {
+        let black = Rgb([0u8, 0u8, 0u8]);
+        let white = Rgb([255u8, 255u8, 255u8]);
+        let mut image = RgbImage::from_pixel(20, 20, white);
+        let fill = Fill { density: Density::Dense, ..Fill::new(Pattern::Vertical, black) };
+        // a triangle with its point at the top, going off the right side of the image
+        fill.fill_polygon(&mut image, &[(0.0, 20.0), (10.0, 0.0), (30.0, 20.0)]);
+
+        assert_eq!(*image.get_pixel(8, 1), white);
+        assert_eq!(*image.get_pixel(8, 19), black);
+        assert_eq!(*image.get_pixel(16, 19), black);
+        assert_eq!(*image.get_pixel(16, 2), white);
+        // only the pattern's columns are inked
+        assert_eq!(*image.get_pixel(9, 19), white);
+    }
+}
@@ -0,0 +1,168 @@
+/// Loads a picture from a local directory or url and converts it into something that can be
+/// displayed on the e-ink display.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, NaiveDate};
+use image::{DynamicImage, RgbImage, Rgb};
+use image::imageops::FilterType;
+use reqwest::blocking::Client;
+
+const PICTURE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+#[derive(Debug, Clone)]
+pub enum PictureSource {
+    /// A directory of png/jpeg files. One is chosen per day, rotating through them in filename
+    /// order.
+    Directory(PathBuf),
+    /// A url that is fetched on every run
+    Url(String),
+}
+
+impl From<&str> for PictureSource {
+    fn from(source: &str) -> PictureSource {
+        if source.starts_with("http://") || source.starts_with("https://") {
+            PictureSource::Url(source.into())
+        }
+        else {
+            PictureSource::Directory(source.into())
+        }
+    }
+}
+
+/// Reads the picture for the given date out of `dir`. Returns None if there are no pictures in
+/// the directory.
+///
+/// This has to be called before the file system is locked down.
+pub fn read_local_picture(dir: &Path, date: NaiveDate) -> Option<Vec<u8>> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+        .expect("failed to read picture directory")
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .filter(|ext| PICTURE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+                .is_some()
+        })
+        .collect();
+
+    if paths.is_empty() {
+        return None;
+    }
+
+    paths.sort();
+    let idx = date.num_days_from_ce() as usize % paths.len();
+    let bytes = std::fs::read(&paths[idx])
+        .expect("failed to read picture file");
+
+    Some(bytes)
+}
+
+/// Fetches the picture at `url`. Returns None if the request fails, since the picture is only a
+/// decoration and the rest of the display shouldn't depend on it.
+pub fn get_picture(url: &str, client: &Client) -> Option<Vec<u8>> {
+    let bytes = client.get(url).send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.bytes())
+        .ok()?;
+    Some(bytes.to_vec())
+}
+
+/// Decodes the picture, or returns None if it isn't an image, e.g. an html error page
+pub fn parse_picture(bytes: &[u8]) -> Option<DynamicImage> {
+    match image::load_from_memory(bytes) {
+        Ok(picture) => Some(picture),
+        Err(err) => {
+            eprintln!("skipping picture that failed to decode: {err}");
+            None
+        }
+    }
+}
+
+/// Scales and crops the picture to fill `width` x `height`, and then dithers it into the
+/// display's white/black/red palette.
+pub fn draw_picture(picture: &DynamicImage, width: u32, height: u32) -> RgbImage {
+    let picture = picture.resize_to_fill(width, height, FilterType::Triangle)
+        .to_rgb8();
+
+    dither_tricolor(&picture)
+}
+
+/// Floyd-Steinberg dithering into the three colors the display supports
+pub fn dither_tricolor(picture: &RgbImage) -> RgbImage {
+    let palette = [
+        [255.0f32, 255.0, 255.0],
+        [0.0, 0.0, 0.0],
+        [255.0, 0.0, 0.0],
+    ];
+
+    let width = picture.width() as usize;
+    let height = picture.height() as usize;
+
+    // working copy of the pixel values with the accumulated error from neighboring pixels
+    let mut values: Vec<[f32; 3]> = picture.pixels()
+        .map(|p| [p[0] as f32, p[1] as f32, p[2] as f32])
+        .collect();
+
+    let mut output = RgbImage::new(width as u32, height as u32);
+    for y in 0..height {
+        for x in 0..width {
+            let old = values[y*width + x];
+            let new = *palette.iter()
+                .min_by(|a, b| color_distance(a, &old).total_cmp(&color_distance(b, &old)))
+                .unwrap();
+            output.put_pixel(x as u32, y as u32, Rgb([new[0] as u8, new[1] as u8, new[2] as u8]));
+
+            let error = [old[0] - new[0], old[1] - new[1], old[2] - new[2]];
+            let mut spread = |dx: i64, dy: i64, fraction: f32| {
+                let nx = x as i64 + dx;
+                let ny = y as i64 + dy;
+                if nx < 0 || nx >= width as i64 || ny >= height as i64 {
+                    return;
+                }
+                let value = &mut values[ny as usize*width + nx as usize];
+                for c in 0..3 {
+                    value[c] += error[c] * fraction;
+                }
+            };
+            spread(1, 0, 7.0/16.0);
+            spread(-1, 1, 3.0/16.0);
+            spread(0, 1, 5.0/16.0);
+            spread(1, 1, 1.0/16.0);
+        }
+    }
+
+    output
+}
+
+fn color_distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    (0..3).map(|c| (a[c] - b[c]).powi(2)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dither_palette() {
+        let picture = RgbImage::from_fn(64, 48, |x, y| {
+            Rgb([(x*4) as u8, (y*5) as u8, ((x+y)*2) as u8])
+        });
+        let dithered = draw_picture(&DynamicImage::ImageRgb8(picture), 40, 30);
+
+        assert_eq!(dithered.dimensions(), (40, 30));
+
+        let white = image::Rgb([255u8, 255u8, 255u8]);
+        let black = image::Rgb([0u8, 0u8, 0u8]);
+        let red = image::Rgb([255u8, 0u8, 0u8]);
+        let colors = [white, black, red];
+        for (x, y, p) in dithered.enumerate_pixels() {
+            assert!(colors.contains(p), "color at {x} {y} did not match: {p:#?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_picture_error_page() {
+        assert!(parse_picture(b"<html><body>404 Not Found</body></html>").is_none());
+    }
+}
@@ -1,13 +1,13 @@
 /// Handles layout and orchestrating the calls from the `draw` module
 
-use crate::DisplayData;
+use crate::{DisplayData, Fonts, Location, SunEvents, moon_phase, sun_events};
 use crate::draw::*;
+use crate::{abbreviate_description, draw_picture, format_daily_temp};
 
-use chrono::{Datelike, DateTime, Duration};
+use chrono::{DateTime, Duration};
 
 use image::{RgbImage, Rgb};
-
-use rusttype::Font;
+use serde_json::Value;
 
 use embedded_graphics::prelude::*;
 use epd_waveshare::{
@@ -19,7 +19,48 @@ use epd_waveshare::buffer_len;
 
 pub type EInkBuffer = Vec<u8>;
 
-pub fn render(current_time: DateTime<chrono_tz::Tz>, display_data: DisplayData) -> (EInkBuffer, RgbImage) {
+/// How many hours the hourly strip covers
+const HOURLY_STRIP_HOURS: usize = 12;
+
+/// Where to show the forecast for the next few hours
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum HourlyLayout {
+    #[default]
+    None,
+    /// In place of the 5 day graph
+    ReplaceGraph,
+    /// Under the 5 day graph, which is made shorter to fit it
+    BelowGraph,
+}
+
+/// Layout and display settings from the env data
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    pub graph: GraphOptions,
+    /// Used for sunrise and sunset times and shading the night on the graph
+    pub location: Option<Location>,
+    /// Show the moon phase next to today's high and low
+    pub moon_phase: bool,
+    pub hourly: HourlyLayout,
+}
+
+impl RenderOptions {
+    pub fn from_json(data: &Value) -> RenderOptions {
+        RenderOptions {
+            graph: GraphOptions::from_json(&data["graph"]),
+            location: Location::from_json(data),
+            moon_phase: data["moon_phase"].as_bool().unwrap_or(false),
+            hourly: match data["hourly"].as_str() {
+                None => HourlyLayout::None,
+                Some("graph") => HourlyLayout::ReplaceGraph,
+                Some("below") => HourlyLayout::BelowGraph,
+                Some(hourly) => panic!("unknown hourly layout {hourly}, expected graph or below"),
+            },
+        }
+    }
+}
+
+pub fn render(current_time: DateTime<chrono_tz::Tz>, display_data: DisplayData, fonts: &Fonts, options: &RenderOptions) -> (EInkBuffer, RgbImage) {
     let white = image::Rgb([255u8, 255u8, 255u8]);
     let black = image::Rgb([0u8, 0u8, 0u8]);
     let red = image::Rgb([255u8, 0u8, 0u8]);
@@ -27,22 +68,43 @@ pub fn render(current_time: DateTime<chrono_tz::Tz>, display_data: DisplayData)
     let graph_x = 50i64;
     let graph_y = 150i64;
     let graph_width = 700i64;
-    let graph_height = 200i64;
+    let show_graph = options.hourly != HourlyLayout::ReplaceGraph;
+    let show_wind_arrows = show_graph && options.graph.wind == WindDisplay::Arrows;
+    let wind_arrows_height = 20i64;
+    let hourly_strip_height = 84i64;
+    // the hourly strip and the wind arrows fit under the graph in the space it would usually take
+    let graph_height = match options.hourly {
+        HourlyLayout::BelowGraph =>
+            200 - hourly_strip_height - 4 - if show_wind_arrows { wind_arrows_height } else { 0 },
+        _ => 200i64,
+    };
     let graph_text_x = graph_x as f32 - 10.0;
     let graph_text_y = graph_y as f32;
 
-    let font_data: &[u8] = include_bytes!("../fonts/Comfortaa-Regular.ttf");
-    let font: Font<'static> = Font::try_from_bytes(font_data)
-        .expect("failed to open font");
-
+    // the picture goes in the bottom right corner, next to the tasks
+    let picture_x = 550i64;
+    let picture_width = 240u32;
+    let picture_bottom = 475i64;
 
     let current_weather = display_data.current_weather;
     let forecast = display_data.forecast;
+    let observations = if options.graph.history { display_data.observations } else { Vec::new() };
     let todoist_tasks = display_data.todoist_tasks;
-
-    let fiveday = draw_5day_graph(&forecast, graph_width, graph_height, &font);
-    let (min_temp, max_temp) = forecast.week_minmax_temps();
-    let daily_temps = forecast.daily_minmax_temps();
+    let daily_forecast = display_data.daily_forecast;
+    // the hour that's in progress is still shown
+    let next_hours: Vec<_> = display_data.hourly_forecast.into_iter()
+        .filter(|p| p.time + Duration::hours(1) > current_time)
+        .take(HOURLY_STRIP_HOURS)
+        .collect();
+    let picture = display_data.picture;
+
+    let graph_context = GraphContext {
+        now: current_time.fixed_offset(),
+        location: options.location.as_ref(),
+        observations: &observations,
+    };
+    let fiveday = draw_5day_graph(&forecast, graph_width, graph_height, &fonts.graph,
+        &graph_context, &options.graph);
 
     let mut image = RgbImage::from_fn(800, 480, |_, _| -> Rgb<u8> { Rgb([255u8, 255u8, 255u8]) });
 
@@ -52,30 +114,126 @@ pub fn render(current_time: DateTime<chrono_tz::Tz>, display_data: DisplayData)
     let temp_size = 150.0;
     let temp_text = format!("{}°", current_weather.temp_f);
 
-    let (today_low, today_high) = daily_temps[&current_time.day()];
-    let today_temps_text = format!("{}° {}°", today_high, today_low);
+    let today_temps = forecast.upcoming_temps(current_time.naive_local());
+    let today_temps_text = format!("{}° {}°",
+        format_daily_temp(today_temps.high), format_daily_temp(today_temps.low));
     let time_text = format!("{}", current_time.format("%-m/%-d  %-I%P"));
 
-    let (temp_width, temp_height) = measure_text(&font, &temp_text, temp_size);
+    let (temp_width, temp_height) = measure_text(&fonts.temperature, &temp_text, temp_size);
     let desc_x = temp_x + temp_width + 10.0;
     let desc_y = temp_y + temp_height/2.0;
 
     let today_temps_x = temp_x + temp_width + 20.0;
     let today_temps_y = 10.0;
 
-    let mintext = min_temp.to_string();
-    let maxtext = max_temp.to_string();
+    // label the graph with the bounds of its axis rather than the temperatures themselves
+    let temp_axis = graph_temp_axis(&forecast, &observations);
+    let mintext = temp_axis.min.to_string();
+    let maxtext = temp_axis.max.to_string();
+
+    let text_style = TextStyle::new(&fonts.header, 36.0, black);
+    let graph_label_style = TextStyle { font: &fonts.graph, size: 24.0, color: red, h_align: HAlign::Right, ..text_style };
+
+    draw_text(&mut image, &temp_text, TextBox::new(temp_x, temp_y, 800.0, 480.0),
+        &TextStyle { font: &fonts.temperature, size: temp_size, ..text_style });
+    let today_temps_box = draw_text(&mut image, &today_temps_text, TextBox::new(today_temps_x, today_temps_y, 800.0, 480.0),
+        &text_style);
+    let mut time_box = draw_text(&mut image, &time_text, TextBox::new(0.0, 10.0, 790.0, 480.0),
+        &TextStyle { h_align: HAlign::Right, ..text_style });
+
+    if let Some(location) = &options.location {
+        let tz = current_time.timezone();
+        let format_time = |t: DateTime<chrono::Utc>| t.with_timezone(&tz).format("%-I:%M%P").to_string();
+        let sun_text = match sun_events(current_time.date_naive(), location) {
+            SunEvents::RiseAndSet { sunrise, sunset } =>
+                format!("sunrise {}  sunset {}", format_time(sunrise), format_time(sunset)),
+            SunEvents::PolarDay => "sun up all day".to_string(),
+            SunEvents::PolarNight => "sun down all day".to_string(),
+        };
+        let sun_box = draw_text(&mut image, &sun_text, TextBox::new(0.0, time_box.bottom + 5.0, 790.0, 480.0),
+            &TextStyle { size: 20.0, h_align: HAlign::Right, ..text_style });
+        // keep the description clear of both lines
+        time_box = TextBox::new(time_box.left.min(sun_box.left), time_box.top, time_box.right, sun_box.bottom);
+    }
+
+    // the moon goes between today's temps and the time, as long as there's room for the icon
+    let moon_x = today_temps_box.right + 20.0;
+    let moon_width = time_box.left - 10.0 - moon_x;
+    let moon_height = 36.0;
+    if options.moon_phase && moon_width >= moon_height {
+        let moon = draw_moon_phase(&moon_phase(&current_time), moon_width as i64, moon_height as i64, &fonts.header);
+        image::imageops::overlay(&mut image, &moon, moon_x as i64, today_temps_box.top as i64);
+    }
+
+    // fit the description between the temperature and the graph, and keep it clear of the time if
+    // they're at the same height
+    let desc_right = if time_box.bottom > desc_y { time_box.left - 10.0 } else { 790.0 };
+    // the rest of the conditions go in a smaller line under the description, if there are any
+    let conditions = current_weather.conditions_line();
+    let conditions_height = if conditions.is_empty() { 0.0 } else { 24.0 };
+    let desc_box = TextBox::new(desc_x, desc_y, desc_right, graph_y as f32 - 5.0 - conditions_height);
+    let desc_style = TextStyle { font: &fonts.description, size: 50.0, max_lines: 2, overflow: Overflow::Fit { min_size: 24.0 }, ..text_style };
+    let description = if text_fits(&current_weather.description, desc_box, &desc_style) {
+        current_weather.description.clone()
+    }
+    else {
+        abbreviate_description(&current_weather.description)
+    };
+    draw_text(&mut image, &description, desc_box, &desc_style);
+    if !conditions.is_empty() {
+        // leave room at the end of the line for the pressure tendency, which goes right after the text
+        let tendency_width = if current_weather.pressure_tendency_inhg.is_some() { 70.0 } else { 0.0 };
+        let conditions_box = draw_text(&mut image, &conditions,
+            TextBox::new(desc_x, desc_box.bottom, 790.0 - tendency_width, graph_y as f32 - 5.0),
+            &TextStyle { size: 20.0, v_align: VAlign::Bottom, overflow: Overflow::ShrinkToFit { min_size: 14.0 }, ..text_style });
+        if let Some(change) = current_weather.pressure_tendency_inhg {
+            let tendency = draw_pressure_tendency(change, tendency_width as i64 - 6, conditions_box.height() as i64, &fonts.header);
+            image::imageops::overlay(&mut image, &tendency, conditions_box.right as i64 + 6, conditions_box.top as i64);
+        }
+    }
+    if show_graph {
+        draw_text(&mut image, &maxtext, TextBox::new(0.0, graph_text_y, graph_text_x, graph_text_y + graph_height as f32),
+            &graph_label_style);
+        draw_text(&mut image, &mintext, TextBox::new(0.0, graph_text_y, graph_text_x, graph_text_y + graph_height as f32),
+            &TextStyle { v_align: VAlign::Bottom, ..graph_label_style });
+    }
+
+    if show_graph && options.graph.rain_labels {
+        let rain_label_box = TextBox::new(graph_x as f32 + graph_width as f32 + 5.0, graph_text_y,
+            800.0, graph_text_y + graph_height as f32);
+        let rain_label_style = TextStyle { font: &fonts.graph, size: 16.0, ..text_style };
+        draw_text(&mut image, "100%", rain_label_box, &rain_label_style);
+        draw_text(&mut image, "50%", rain_label_box, &TextStyle { v_align: VAlign::Center, ..rain_label_style });
+        draw_text(&mut image, "0%", rain_label_box, &TextStyle { v_align: VAlign::Bottom, ..rain_label_style });
+    }
 
-    draw_text_left(&mut image, &temp_text, temp_x, temp_y, &font, temp_size);
-    draw_text_left(&mut image, &current_weather.description, desc_x, desc_y, &font, 50.0);
-    draw_text_left(&mut image, &today_temps_text, today_temps_x, today_temps_y, &font, 36.0);
-    draw_text_right(&mut image, &time_text, 790.0, 10.0, &font, 36.0, black);
-    draw_text_right(&mut image, &maxtext, graph_text_x, graph_text_y, &font, 24.0, red);
-    draw_text_bottom_right(&mut image, &mintext, graph_text_x, graph_text_y+graph_height as f32, &font, 24.0, red);
 
+    // the wind arrows go directly under the graph, pushing the tasks down
+    let wind_arrows_y = graph_y + graph_height + 4;
+    let wind_arrows = show_wind_arrows
+        .then(|| draw_wind_arrows(&forecast, graph_width, wind_arrows_height, &options.graph));
+    let mut tasks_offset = if wind_arrows.is_some() { wind_arrows_height } else { 0 };
+
+    let (hourly_strip_y, hourly_strip) = match options.hourly {
+        HourlyLayout::None => (0, None),
+        HourlyLayout::ReplaceGraph => (graph_y, Some(draw_hourly_strip(&next_hours, graph_width, graph_height,
+            &fonts.graph, options.location.as_ref()))),
+        HourlyLayout::BelowGraph => {
+            let y = wind_arrows_y + tasks_offset;
+            tasks_offset += hourly_strip_height + 4;
+            (y, Some(draw_hourly_strip(&next_hours, graph_width, hourly_strip_height,
+                &fonts.graph, options.location.as_ref())))
+        }
+    };
 
-    let mut task_y = (graph_y + graph_height + 20) as f32;
+    let mut task_y = (graph_y + graph_height + 20 + tasks_offset) as f32;
+    // the picture starts under the wind arrows and the hourly strip too, and gets shorter to fit
+    let picture_y = graph_y + graph_height + 10 + tasks_offset;
+    let picture_height = (picture_bottom - picture_y) as u32;
     let task_x = 50.0;
+    // leave room for the picture if there is one
+    let task_right = if picture.is_some() { picture_x as f32 - 10.0 } else { 790.0 };
+    let task_style = TextStyle { font: &fonts.tasks, size: 24.0, overflow: Overflow::Ellipsis, ..text_style };
     let current_date = current_time.date_naive();
     for task in todoist_tasks {
         let date_desc: String;
@@ -92,8 +250,8 @@ pub fn render(current_time: DateTime<chrono_tz::Tz>, display_data: DisplayData)
             date_desc = task.due_date.format("%-m/%-d").to_string();
         }
 
-        draw_text_left(&mut image, &date_desc, task_x, task_y, &font, 24.0);
-        draw_text_left(&mut image, &task.description, task_x+150.0, task_y, &font, 24.0);
+        draw_text(&mut image, &date_desc, TextBox::new(task_x, task_y, task_x+150.0, task_y+30.0), &task_style);
+        draw_text(&mut image, &task.description, TextBox::new(task_x+150.0, task_y, task_right, task_y+30.0), &task_style);
         task_y += 30.0;
 
         if task_y >= 480.0 {
@@ -101,7 +259,33 @@ pub fn render(current_time: DateTime<chrono_tz::Tz>, display_data: DisplayData)
         }
     }
 
-    image::imageops::overlay(&mut image, &fiveday, graph_x, graph_y);
+    // fill the space under the tasks with the detailed forecast for the next couple of periods, as
+    // long as there's room for a few lines of it
+    let forecast_text_y = task_y + 10.0;
+    let forecast_text_height = 475.0 - forecast_text_y;
+    let upcoming: Vec<_> = daily_forecast.into_iter()
+        .filter(|p| p.end_time > current_time)
+        .take(2)
+        .collect();
+    if forecast_text_height >= 50.0 && !upcoming.is_empty() {
+        let forecast_text = draw_forecast_text(&upcoming, (task_right - task_x) as i64, forecast_text_height as i64, &fonts.tasks);
+        image::imageops::overlay(&mut image, &forecast_text, task_x as i64, forecast_text_y as i64);
+    }
+
+    if show_graph {
+        image::imageops::overlay(&mut image, &fiveday, graph_x, graph_y);
+    }
+    if let Some(wind_arrows) = wind_arrows {
+        image::imageops::overlay(&mut image, &wind_arrows, graph_x, wind_arrows_y);
+    }
+    if let Some(hourly_strip) = hourly_strip {
+        image::imageops::overlay(&mut image, &hourly_strip, graph_x, hourly_strip_y);
+    }
+
+    if let Some(picture) = picture {
+        let picture = draw_picture(&picture, picture_width, picture_height);
+        image::imageops::overlay(&mut image, &picture, picture_x, picture_y);
+    }
 
 
     let mut buffer = vec![TriColor::White.get_byte_value(); buffer_len(EPD_WIDTH as usize, 2 * EPD_HEIGHT as usize)];
@@ -131,6 +315,7 @@ pub fn render(current_time: DateTime<chrono_tz::Tz>, display_data: DisplayData)
 #[cfg(test)]
 mod tests {
     use crate::*;
+    use chrono::{Datelike, Timelike};
 
     fn read_image_data(bytes: &[u8]) -> image::RgbImage {
         let decoder = image::codecs::png::PngDecoder::new(bytes).unwrap();
@@ -140,14 +325,25 @@ mod tests {
             .clone()
     }
 
+    /// Renders `data` at `test_render_time` and compares it to `gold_master`
+    fn check_render(data: DisplayData, gold_master: &[u8]) {
+        check_render_with_options(data, &RenderOptions::default(), gold_master);
+    }
+
+    fn check_render_with_options(data: DisplayData, options: &RenderOptions, gold_master: &[u8]) {
+        let current_time = test_render_time();
+        let (buffer, image) = render(current_time, data, &Fonts::default(), options);
+        assert_eq!(buffer.len(), 96000);
+        assert_eq!(image, read_image_data(gold_master));
+    }
+
     #[test]
     fn test_render1() {
         let gold_master = include_bytes!("../tests/render_test1.png");
 
-        let current_time = chrono::DateTime::parse_from_rfc3339("2023-10-16T20:30:00-04:00").unwrap()
-            .with_timezone(&chrono_tz::Tz::America__New_York);
+        let current_time = test_render_time();
         let data = get_test_data();
-        let (buffer, image) = render(current_time, data);
+        let (buffer, image) = render(current_time, data, &Fonts::default(), &RenderOptions::default());
 
         // code to generate the initial file
         // use std::path::Path;
@@ -165,4 +361,124 @@ mod tests {
             assert!(colors.contains(p), "color at {x} {y} did not match: {p:#?}");
         }
     }
+
+    #[test]
+    fn test_render_cold_week() {
+        // from about -30 to -10
+        check_render(get_test_data_with_temps(|t| t - 80), include_bytes!("../tests/render_test_cold.png"));
+    }
+
+    #[test]
+    fn test_render_constant_week() {
+        // the same temperature all week, which is a range of zero
+        check_render(get_test_data_with_temps(|_| 0), include_bytes!("../tests/render_test_constant.png"));
+    }
+
+    #[test]
+    fn test_render_huge_swing_week() {
+        // from about -55 to 50
+        check_render(get_test_data_with_temps(|t| (t - 60) * 6), include_bytes!("../tests/render_test_swing.png"));
+    }
+
+    #[test]
+    fn test_render_few_tasks() {
+        // with only one task the detailed forecast fills the space under it
+        let mut data = get_test_data();
+        data.todoist_tasks.truncate(1);
+        check_render(data, include_bytes!("../tests/render_test_few_tasks.png"));
+    }
+
+    #[test]
+    fn test_render_graph_options() {
+        let options = RenderOptions {
+            graph: GraphOptions { hour_ticks: true, now_marker: true, temp_gridlines: true, rain_labels: true, ..Default::default() },
+            ..Default::default()
+        };
+        check_render_with_options(get_test_data(), &options, include_bytes!("../tests/render_test_graph_options.png"));
+    }
+
+    #[test]
+    fn test_render_wind_arrows_and_picture() {
+        // the picture is moved down under the arrows
+        let mut data = get_test_data_with_points(|p| ForecastPoint {
+            wind_speed: Some(p.time.hour() as u64),
+            wind_direction: Some(p.time.hour() as f32 * 15.0),
+            ..p
+        });
+        data.picture = Some(image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(300, 150,
+            |x, y| image::Rgb([(x * 255 / 300) as u8, (y * 255 / 150) as u8, 128u8]))));
+        let options = RenderOptions {
+            graph: GraphOptions { wind: WindDisplay::Arrows, ..Default::default() },
+            ..Default::default()
+        };
+        check_render_with_options(data, &options, include_bytes!("../tests/render_test_wind_picture.png"));
+    }
+
+    #[test]
+    fn test_render_precip_legend() {
+        // the legend goes over the snow and sleet shading but under the hour ticks and the wind
+        // line, which are at the bottom of the graph where it goes
+        let data = get_test_data_with_points(|p| ForecastPoint {
+            wind_speed: Some(if p.time.day() == 12 { 1 } else { 10 }),
+            short_desc: match p.time.day() {
+                14 => Some("Snow Showers".into()),
+                15 => Some("Sleet".into()),
+                _ => Some("Rain".into()),
+            },
+            ..p
+        });
+        let options = RenderOptions {
+            graph: GraphOptions { hour_ticks: true, wind: WindDisplay::Series, ..Default::default() },
+            ..Default::default()
+        };
+        check_render_with_options(data, &options, include_bytes!("../tests/render_test_precip_legend.png"));
+    }
+
+    #[test]
+    fn test_render_precip_amounts() {
+        // rain through the 13th that's heaviest in the afternoon, and snow through the 15th
+        let data = get_test_data_with_points(|p| ForecastPoint {
+            precip_in: match p.time.day() {
+                13 if (12..18).contains(&p.time.hour()) => Some(0.15),
+                13 | 15 => Some(0.02),
+                _ => Some(0.0),
+            },
+            snowfall_in: Some(if p.time.day() == 15 { 0.2 } else { 0.0 }),
+            ..p
+        });
+        let options = RenderOptions {
+            graph: GraphOptions { precip_amounts: true, ..Default::default() },
+            ..Default::default()
+        };
+        check_render_with_options(data, &options, include_bytes!("../tests/render_test_precip_amounts.png"));
+    }
+
+    /// Test data with descriptions and sky cover for the hourly strip's icons, and wind for the
+    /// arrows
+    fn get_hourly_test_data() -> DisplayData {
+        get_test_data_with_points(|p| ForecastPoint {
+            short_desc: Some(if p.rain_prob >= 30 { "Chance Rain Showers" } else { "Partly Cloudy" }.into()),
+            sky_cover: Some(p.rain_prob * 2),
+            wind_speed: Some(p.time.hour() as u64),
+            wind_direction: Some(p.time.hour() as f32 * 15.0),
+            ..p
+        })
+    }
+
+    #[test]
+    fn test_render_hourly_graph() {
+        let options = RenderOptions { hourly: HourlyLayout::ReplaceGraph, ..Default::default() };
+        check_render_with_options(get_hourly_test_data(), &options, include_bytes!("../tests/render_test_hourly_graph.png"));
+    }
+
+    #[test]
+    fn test_render_hourly_below() {
+        // the graph is made shorter to fit both the wind arrows and the hourly strip
+        let options = RenderOptions {
+            graph: GraphOptions { wind: WindDisplay::Arrows, ..Default::default() },
+            hourly: HourlyLayout::BelowGraph,
+            ..Default::default()
+        };
+        check_render_with_options(get_hourly_test_data(), &options, include_bytes!("../tests/render_test_hourly_below.png"));
+    }
 }
@@ -0,0 +1,129 @@
+/// Sunrise and sunset times calculated locally from the latitude and longitude, using the
+/// sunrise equation from https://en.wikipedia.org/wiki/Sunrise_equation
+
+use chrono::prelude::*;
+use serde_json::Value;
+
+/// Julian date of the J2000 epoch, 2000-01-01 12:00 UTC
+const J2000: f64 = 2451545.0;
+/// Julian date of the unix epoch
+const UNIX_EPOCH_JULIAN: f64 = 2440587.5;
+/// Angle of the sun's center below the horizon at sunrise and sunset, accounting for refraction
+/// and the size of the sun's disc
+const SUNRISE_ALTITUDE: f64 = -0.833;
+/// Tilt of the earth's axis
+const AXIAL_TILT: f64 = 23.4397;
+
+/// Where the display is, for calculating sunrise and sunset
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    /// Degrees north of the equator
+    pub latitude: f64,
+    /// Degrees east of Greenwich, so the Americas are negative
+    pub longitude: f64,
+}
+
+impl Location {
+    /// Reads `latitude` and `longitude` from the env data. Returns None if neither is set.
+    pub fn from_json(data: &Value) -> Option<Location> {
+        match (data["latitude"].as_f64(), data["longitude"].as_f64()) {
+            (Some(latitude), Some(longitude)) => Some(Location { latitude, longitude }),
+            (None, None) => None,
+            _ => panic!("latitude and longitude must both be set"),
+        }
+    }
+}
+
+/// When the sun rises and sets on a given day
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SunEvents {
+    RiseAndSet {
+        sunrise: DateTime<Utc>,
+        sunset: DateTime<Utc>,
+    },
+    /// The sun doesn't set
+    PolarDay,
+    /// The sun doesn't rise
+    PolarNight,
+}
+
+/// Calculates sunrise and sunset at `location` on `date`
+pub fn sun_events(date: NaiveDate, location: &Location) -> SunEvents {
+    let days_since_unix_epoch = (date - NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()).num_days() as f64;
+    // days since J2000 at noon on `date`, with a small correction for leap seconds
+    let n = (days_since_unix_epoch + UNIX_EPOCH_JULIAN + 0.5 - J2000 + 0.0008).round();
+
+    // mean solar time at the location
+    let mean_solar_time = n - location.longitude / 360.0;
+    let mean_anomaly = (357.5291 + 0.98560028 * mean_solar_time).rem_euclid(360.0);
+    let m = mean_anomaly.to_radians();
+    let center = 1.9148 * m.sin() + 0.0200 * (2.0 * m).sin() + 0.0003 * (3.0 * m).sin();
+    let ecliptic_longitude = (mean_anomaly + center + 180.0 + 102.9372).rem_euclid(360.0).to_radians();
+    let transit = J2000 + mean_solar_time + 0.0053 * m.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+    let declination = (ecliptic_longitude.sin() * AXIAL_TILT.to_radians().sin()).asin();
+    let latitude = location.latitude.to_radians();
+    let cos_hour_angle = (SUNRISE_ALTITUDE.to_radians().sin() - latitude.sin() * declination.sin())
+        / (latitude.cos() * declination.cos());
+
+    if cos_hour_angle > 1.0 {
+        return SunEvents::PolarNight;
+    }
+    if cos_hour_angle < -1.0 {
+        return SunEvents::PolarDay;
+    }
+
+    let hour_angle = cos_hour_angle.acos().to_degrees();
+    SunEvents::RiseAndSet {
+        sunrise: julian_to_utc(transit - hour_angle / 360.0),
+        sunset: julian_to_utc(transit + hour_angle / 360.0),
+    }
+}
+
+/// Returns true if the sun is down at `time`, using the sunrise and sunset on `time`'s local date
+pub fn is_night<Tz: TimeZone>(time: &DateTime<Tz>, location: &Location) -> bool {
+    match sun_events(time.date_naive(), location) {
+        SunEvents::RiseAndSet { sunrise, sunset } => {
+            let time = time.with_timezone(&Utc);
+            time < sunrise || time >= sunset
+        }
+        SunEvents::PolarDay => false,
+        SunEvents::PolarNight => true,
+    }
+}
+
+fn julian_to_utc(julian_date: f64) -> DateTime<Utc> {
+    let seconds = ((julian_date - UNIX_EPOCH_JULIAN) * 86400.0).round() as i64;
+    DateTime::from_timestamp(seconds, 0).expect("sun event out of range")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sun_events() {
+        let washington = Location { latitude: 38.9, longitude: -77.04 };
+        let date = NaiveDate::from_ymd_opt(2023, 10, 16).unwrap();
+        let (sunrise, sunset) = match sun_events(date, &washington) {
+            SunEvents::RiseAndSet { sunrise, sunset } => (sunrise, sunset),
+            events => panic!("expected sunrise and sunset, got {events:?}"),
+        };
+        // 7:17am and 6:29pm EDT
+        let expected_sunrise = Utc.with_ymd_and_hms(2023, 10, 16, 11, 17, 0).unwrap();
+        let expected_sunset = Utc.with_ymd_and_hms(2023, 10, 16, 22, 29, 0).unwrap();
+        assert!((sunrise - expected_sunrise).num_minutes().abs() <= 2, "sunrise was {sunrise}");
+        assert!((sunset - expected_sunset).num_minutes().abs() <= 2, "sunset was {sunset}");
+
+        let evening = DateTime::parse_from_rfc3339("2023-10-16T20:30:00-04:00").unwrap();
+        assert!(is_night(&evening, &washington));
+        let noon = DateTime::parse_from_rfc3339("2023-10-16T12:00:00-04:00").unwrap();
+        assert!(!is_night(&noon, &washington));
+
+        let tromso = Location { latitude: 69.65, longitude: 18.96 };
+        let winter = NaiveDate::from_ymd_opt(2023, 12, 21).unwrap();
+        let summer = NaiveDate::from_ymd_opt(2023, 6, 21).unwrap();
+        assert_eq!(sun_events(winter, &tromso), SunEvents::PolarNight);
+        assert_eq!(sun_events(summer, &tromso), SunEvents::PolarDay);
+    }
+}
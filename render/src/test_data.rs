@@ -1,6 +1,8 @@
-use chrono::{DateTime, FixedOffset};
+use chrono::DateTime;
 
-pub fn test_data1() -> Vec<(DateTime<FixedOffset>, i32, u64)> {
+use crate::{ForecastPeriod, ForecastPoint};
+
+pub fn test_data1() -> Vec<ForecastPoint> {
     vec![
         (
             "2023-10-12T01:00:00-04:00",
@@ -602,5 +604,59 @@ pub fn test_data1() -> Vec<(DateTime<FixedOffset>, i32, u64)> {
             54,
             32,
         ),
-    ].into_iter().map(|(dt, t, r)| (DateTime::parse_from_rfc3339(dt).unwrap(), t, r)).collect()
+    ].into_iter().map(|(dt, t, r)| ForecastPoint::new(DateTime::parse_from_rfc3339(dt).unwrap(), t, r)).collect()
+}
+
+/// The first periods of the daily forecast at the same time as `test_data1`, from the morning of
+/// its last day
+pub fn test_daily_forecast() -> Vec<ForecastPeriod> {
+    vec![
+        (
+            "Today",
+            "2023-10-16T06:00:00-04:00",
+            "2023-10-16T18:00:00-04:00",
+            59,
+            "Partly Sunny",
+            "Partly sunny, with a high near 59. Southwest wind around 5 mph.",
+        ),
+        (
+            "Tonight",
+            "2023-10-16T18:00:00-04:00",
+            "2023-10-17T06:00:00-04:00",
+            51,
+            "Mostly Cloudy",
+            "Mostly cloudy, with a low around 51. Southwest wind around 5 mph.",
+        ),
+        (
+            "Tuesday",
+            "2023-10-17T06:00:00-04:00",
+            "2023-10-17T18:00:00-04:00",
+            63,
+            "Chance Rain Showers",
+            "A chance of rain showers after 2pm. Partly sunny, with a high near 63. West wind 5 to 10 mph. Chance of precipitation is 30%.",
+        ),
+        (
+            "Tuesday Night",
+            "2023-10-17T18:00:00-04:00",
+            "2023-10-18T06:00:00-04:00",
+            52,
+            "Partly Cloudy",
+            "Partly cloudy, with a low around 52. West wind around 5 mph.",
+        ),
+    ].into_iter().map(|(name, start, end, t, short_desc, long_desc)| ForecastPeriod {
+        period_name: Some(name.into()),
+        start_time: DateTime::parse_from_rfc3339(start).unwrap(),
+        end_time: DateTime::parse_from_rfc3339(end).unwrap(),
+        temp_f: t,
+        rain_prob: 0,
+        wind_speed: 5,
+        wind_direction: None,
+        dewpoint_f: None,
+        short_desc: short_desc.into(),
+        long_desc: Some(long_desc.into()),
+        precip_in: None,
+        snowfall_in: None,
+        sky_cover: None,
+        apparent_temp_f: None,
+    }).collect()
 }
@@ -2,27 +2,99 @@ use imageproc::drawing::Canvas;
 
 use image::{RgbImage, Rgb};
 
-use rusttype::{point, Font, PositionedGlyph, Rect, Scale};
+use rusttype::{point, Font, GlyphId, PositionedGlyph, Rect, Scale};
+
+use crate::{BitmapGlyph, FontChain, FontFace};
 use std::cmp::max;
 
 // Code mostly taken wholesale from 
 // https://github.com/image-rs/imageproc/blob/master/src/drawing/text.rs
 
-fn layout_glyphs(
+/// A glyph with its position worked out, from either kind of font
+enum LaidOutGlyph<'a> {
+    Outline(PositionedGlyph<'a>),
+    /// A bitmap glyph drawn with each of its pixels scaled up to `scale` x `scale`
+    Bitmap { glyph: &'a BitmapGlyph, scale: u32 },
+}
+
+impl LaidOutGlyph<'_> {
+    /// Calls `o` with the coordinates relative to the glyph's pixel bounding box and the coverage
+    /// of each pixel in the glyph.
+    fn draw(&self, mut o: impl FnMut(u32, u32, f32)) {
+        match self {
+            LaidOutGlyph::Outline(g) => g.draw(o),
+            LaidOutGlyph::Bitmap { glyph, scale } => {
+                for y in 0..glyph.height*scale {
+                    for x in 0..glyph.width*scale {
+                        if glyph.pixel(x/scale, y/scale) {
+                            o(x, y, 1.0);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Lays out `text` on a single line, taking each glyph from the first font in the chain that has
+/// it. Kerning is only applied between glyphs from the same font, and every glyph sits on the
+/// primary font's baseline.
+fn layout_glyphs<'a>(
     scale: Scale,
-    font: &Font,
+    font: &'a FontChain,
     text: &str,
-    mut f: impl FnMut(PositionedGlyph, Rect<i32>),
+    mut f: impl FnMut(LaidOutGlyph<'a>, Rect<i32>),
 ) -> (i32, i32) {
-    let v_metrics = font.v_metrics(scale);
+    let primary = font.primary();
+    let v_metrics = primary.v_metrics(scale);
 
     let (mut w, mut h) = (0, 0);
+    let mut use_bb = |g: LaidOutGlyph<'a>, bb: Rect<i32>| {
+        w = max(w, bb.max.x);
+        h = max(h, bb.max.y);
+        f(g, bb);
+    };
 
-    for g in font.layout(text, scale, point(0.0, v_metrics.ascent)) {
-        if let Some(bb) = g.pixel_bounding_box() {
-            w = max(w, bb.max.x);
-            h = max(h, bb.max.y);
-            f(g, bb);
+    let mut caret = 0.0;
+    let mut last_glyph: Option<(&Font, GlyphId)> = None;
+    for c in text.chars() {
+        match font.font_for(c).unwrap_or(primary) {
+            FontFace::TrueType(glyph_font) => {
+                let g = glyph_font.glyph(c).scaled(scale);
+                if let Some((last_font, last_id)) = last_glyph {
+                    if std::ptr::eq(last_font, glyph_font) {
+                        caret += glyph_font.pair_kerning(scale, last_id, g.id());
+                    }
+                }
+                let g = g.positioned(point(caret, v_metrics.ascent));
+                caret += g.unpositioned().h_metrics().advance_width;
+                last_glyph = Some((glyph_font, g.id()));
+
+                if let Some(bb) = g.pixel_bounding_box() {
+                    use_bb(LaidOutGlyph::Outline(g), bb);
+                }
+            }
+            FontFace::Bitmap(glyph_font) => {
+                last_glyph = None;
+                // the primary font may not have the glyph either
+                let glyph = match glyph_font.glyph(c) {
+                    Some(glyph) => glyph,
+                    None => continue,
+                };
+                let pixel_scale = glyph_font.pixel_scale(scale.y);
+
+                let x = caret.round() as i32 + glyph.left*pixel_scale;
+                let y = v_metrics.ascent.round() as i32 - glyph.top*pixel_scale;
+                caret += (glyph.advance*pixel_scale) as f32;
+
+                if glyph.width > 0 && glyph.height > 0 {
+                    let bb = Rect {
+                        min: point(x, y),
+                        max: point(x + glyph.width as i32*pixel_scale, y + glyph.height as i32*pixel_scale),
+                    };
+                    use_bb(LaidOutGlyph::Bitmap { glyph, scale: pixel_scale as u32 }, bb);
+                }
+            }
         }
     }
 
@@ -40,7 +112,7 @@ pub fn draw_text_mut<'a>(
     x: i32,
     y: i32,
     scale: Scale,
-    font: &'a Font<'a>,
+    font: &'a FontChain,
     text: &'a str,
 ) where
 {
@@ -66,3 +138,101 @@ pub fn draw_text_mut<'a>(
         })
     });
 }
+
+/// Returns the (width, height) of `text` in pixels. The width is measured between the pixel
+/// bounding boxes of the first and last visible glyphs, and the height is the font's full line
+/// height.
+pub fn measure_text(font: &FontChain, text: &str, scale: Scale) -> (f32, f32) {
+    let v_metrics = font.v_metrics(scale);
+    let height = (v_metrics.ascent - v_metrics.descent).ceil();
+
+    let mut min_x = None;
+    let mut max_x = 0;
+    layout_glyphs(scale, font, text, |_g, bb| {
+        min_x.get_or_insert(bb.min.x);
+        max_x = bb.max.x;
+    });
+    let width = min_x.map(|min_x| (max_x - min_x) as f32).unwrap_or(0.0);
+
+    (width, height)
+}
+
+/// Breaks `text` into lines no wider than `max_width`, at whitespace where possible. Words that
+/// are too long to fit on a line by themselves are broken between characters.
+pub fn wrap_text(font: &FontChain, text: &str, scale: Scale, max_width: f32) -> Vec<String> {
+    let fits = |s: &str| measure_text(font, s, scale).0 <= max_width;
+
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() { word.to_string() } else { format!("{line} {word}") };
+        if fits(&candidate) {
+            line = candidate;
+            continue;
+        }
+
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        for c in word.chars() {
+            line.push(c);
+            if !fits(&line) && line.chars().count() > 1 {
+                line.pop();
+                lines.push(std::mem::take(&mut line));
+                line.push(c);
+            }
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+/// Wraps `text` into at most `max_lines` lines, and if there is text left over replaces the end
+/// of the last line with an ellipsis.
+pub fn wrap_ellipsis(font: &FontChain, text: &str, scale: Scale, max_width: f32, max_lines: usize) -> Vec<String> {
+    let mut lines = wrap_text(font, text, scale, max_width);
+    if lines.len() <= max_lines {
+        return lines;
+    }
+    lines.truncate(max_lines);
+
+    let ellipsis = if font.font_for('…').is_some() { "…" } else { "..." };
+    let last = lines.last_mut().unwrap();
+    loop {
+        let candidate = format!("{}{ellipsis}", last.trim_end());
+        if measure_text(font, &candidate, scale).0 <= max_width || last.is_empty() {
+            *last = candidate;
+            break;
+        }
+        last.pop();
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_ellipsis() {
+        let font = crate::Fonts::default().tasks;
+        let scale = Scale::uniform(24.0);
+        let text = "pick up the dry cleaning before the store closes on saturday";
+
+        let lines = wrap_text(&font, text, scale, 200.0);
+        assert!(lines.len() > 2);
+        assert_eq!(lines.join(" "), text);
+        for line in &lines {
+            assert!(measure_text(&font, line, scale).0 <= 200.0, "line too wide: {line}");
+        }
+
+        let lines = wrap_ellipsis(&font, text, scale, 200.0, 2);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].ends_with("…") || lines[1].ends_with("..."));
+        assert!(measure_text(&font, &lines[1], scale).0 <= 200.0);
+    }
+}
@@ -1,27 +1,48 @@
-use crate::{CurrentWeather, DisplayData, Task, Forecast5Day};
-use crate::test_data::test_data1;
+use crate::{CurrentWeather, DisplayData, Task, ForecastOptions, ForecastWindow};
+use crate::test_data::{test_daily_forecast, test_data1};
 
 use chrono::Duration;
 //use chrono::{Duration, DateTime};
 
+/// The time to render the test data at. It's the morning of the last day in `test_data1`, so
+/// the header has a high and low and the hourly strip has the next 12 hours.
+pub(crate) fn test_render_time() -> chrono::DateTime<chrono_tz::Tz> {
+    chrono::DateTime::parse_from_rfc3339("2023-10-16T07:30:00-04:00").unwrap()
+        .with_timezone(&chrono_tz::Tz::America__New_York)
+}
+
 pub(crate) fn get_test_data() -> DisplayData {
-    let current_weather = CurrentWeather {
-        description: "test data".into(),
-        temp_f: 69,
-        rain_in: 0,
-    };
+    let current_weather = CurrentWeather::new("test data".into(), 69, Some(0.0));
 
     let full_forecast = test_data1();
-    let forecast = Forecast5Day { full_forecast };
+    let forecast = ForecastWindow::from_points(full_forecast.clone(), &ForecastOptions::default());
 
-    let today = chrono::DateTime::parse_from_rfc3339("2023-10-16T20:30:00-04:00").unwrap()
-        .with_timezone(&chrono_tz::Tz::America__New_York)
-        .date_naive();
+    let today = test_render_time().date_naive();
     let yesterday = today - Duration::days(1);
     let tomorrow = today + Duration::days(1);
     let todoist_tasks = vec![Task { description: "test task".into(), due_date: today },
     Task { description: "task 2".into(), due_date: yesterday },
     Task { description: "task 3".into(), due_date: tomorrow }];
 
-    DisplayData {current_weather, forecast, todoist_tasks }
+    DisplayData {current_weather, forecast, hourly_forecast: full_forecast,
+        daily_forecast: test_daily_forecast(), observations: Vec::new(), todoist_tasks, picture: None }
+}
+
+/// The same as `get_test_data` but with every point in the forecast passed through `point`
+#[cfg(test)]
+pub(crate) fn get_test_data_with_points(point: impl Fn(crate::ForecastPoint) -> crate::ForecastPoint) -> DisplayData {
+    let mut data = get_test_data();
+    let full_forecast: Vec<crate::ForecastPoint> = test_data1().into_iter().map(point).collect();
+    data.forecast = ForecastWindow::from_points(full_forecast.clone(), &ForecastOptions::default());
+    data.hourly_forecast = full_forecast;
+    data
+}
+
+/// The same as `get_test_data` but with every temperature in the forecast, and the current
+/// temperature, passed through `temp`
+#[cfg(test)]
+pub(crate) fn get_test_data_with_temps(temp: impl Fn(i32) -> i32) -> DisplayData {
+    let mut data = get_test_data_with_points(|p| crate::ForecastPoint { temp_f: temp(p.temp_f), ..p });
+    data.current_weather.temp_f = temp(data.current_weather.temp_f);
+    data
 }
@@ -8,115 +8,657 @@ use serde_json::Value;
 
 use crate::EnvData;
 
-const OBSERVATION_DATA_URL: &'static str = "https://api.weather.gov/stations/{station}/observations?limit=1";
+const OBSERVATION_DATA_URL: &'static str = "https://api.weather.gov/stations/{station}/observations";
+/// How far back to get observations for
+const OBSERVATION_HISTORY_HOURS: i64 = 24;
 const DAILY_FORECAST_URL: &'static str = "https://api.weather.gov/gridpoints/{office}/{gridpoint}/forecast";
 const HOURLY_FORECAST_URL: &'static str = "https://api.weather.gov/gridpoints/{office}/{gridpoint}/forecast/hourly";
+const MPH_PER_KMH: f64 = 0.621371;
+const PA_PER_INHG: f64 = 3386.39;
+/// How far back the pressure tendency looks
+const PRESSURE_TENDENCY_HOURS: i64 = 3;
+/// How far from exactly `PRESSURE_TENDENCY_HOURS` before the latest reading the earlier one can be
+const PRESSURE_TENDENCY_TOLERANCE_MINUTES: i64 = 30;
+/// Pressure changes smaller than this over the tendency period count as steady
+const PRESSURE_STEADY_INHG: f32 = 0.02;
 
 
-pub type FullForecast = Vec<(DateTime<FixedOffset>, i32, u64)>;
-pub type FilteredForecast = Vec<(DateTime<FixedOffset>, i32, u64)>;
+pub type FullForecast = Vec<ForecastPoint>;
+pub type FilteredForecast = Vec<ForecastPoint>;
 
-// number of data points to filter by (average rain probabilities etc)
-const CHUNK_SIZE: usize = 3;
+/// A single point in time in the forecast. Fields that not every source has are optional.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForecastPoint {
+    pub time: DateTime<FixedOffset>,
+    pub temp_f: i32,
+    /// percentage out of 100
+    pub rain_prob: u64,
+    /// inches of liquid precipitation
+    pub precip_in: Option<f32>,
+    /// inches of snow
+    pub snowfall_in: Option<f32>,
+    /// mph
+    pub wind_speed: Option<u64>,
+    /// The direction the wind is coming from in degrees clockwise from north
+    pub wind_direction: Option<f32>,
+    /// percentage out of 100
+    pub sky_cover: Option<u64>,
+    pub dewpoint_f: Option<i32>,
+    /// What the temperature feels like with the wind chill or heat index
+    pub apparent_temp_f: Option<i32>,
+    pub short_desc: Option<String>,
+}
+
+/// What kind of precipitation is forecast
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrecipType {
+    Rain,
+    Snow,
+    /// Sleet, freezing rain, or rain and snow together
+    Mixed,
+}
+
+/// Words in the short description for each kind of precipitation. Mixed is checked first since
+/// descriptions like "Rain And Snow" also have the words for the others in them. Freezing and ice
+/// are only matched along with the kind of precipitation, since freezing fog and ice fog aren't.
+const MIXED_WORDS: [&str; 7] = ["sleet", "freezing rain", "freezing drizzle", "ice pellets", "wintry mix", "and snow", "snow and rain"];
+const SNOW_WORDS: [&str; 3] = ["snow", "flurries", "blizzard"];
+const RAIN_WORDS: [&str; 5] = ["rain", "shower", "drizzle", "thunderstorm", "t-storm"];
+
+impl ForecastPoint {
+    /// A point with only the temperature and rain probability
+    pub fn new(time: DateTime<FixedOffset>, temp_f: i32, rain_prob: u64) -> ForecastPoint {
+        ForecastPoint {
+            time,
+            temp_f,
+            rain_prob,
+            precip_in: None,
+            snowfall_in: None,
+            wind_speed: None,
+            wind_direction: None,
+            sky_cover: None,
+            dewpoint_f: None,
+            apparent_temp_f: None,
+            short_desc: None,
+        }
+    }
+
+    /// Guesses the kind of precipitation from the short description, using the snowfall amount to
+    /// catch snow the description doesn't mention. Returns None if neither says anything about it.
+    pub fn precip_type(&self) -> Option<PrecipType> {
+        let desc = self.short_desc.as_deref().unwrap_or("").to_lowercase();
+        let has_any = |words: &[&str]| words.iter().any(|w| desc.contains(w));
+        let from_desc = if has_any(&MIXED_WORDS) {
+            Some(PrecipType::Mixed)
+        }
+        else if has_any(&SNOW_WORDS) {
+            Some(PrecipType::Snow)
+        }
+        else if has_any(&RAIN_WORDS) {
+            Some(PrecipType::Rain)
+        }
+        else {
+            None
+        };
+
+        let snowing = self.snowfall_in.filter(|s| *s > 0.0).is_some();
+        match from_desc {
+            Some(PrecipType::Rain) if snowing => Some(PrecipType::Mixed),
+            None if snowing => Some(PrecipType::Snow),
+            _ => from_desc,
+        }
+    }
+}
+
+impl From<&ForecastPeriod> for ForecastPoint {
+    fn from(period: &ForecastPeriod) -> ForecastPoint {
+        ForecastPoint {
+            precip_in: period.precip_in,
+            snowfall_in: period.snowfall_in,
+            wind_speed: Some(period.wind_speed),
+            wind_direction: period.wind_direction,
+            sky_cover: period.sky_cover,
+            dewpoint_f: period.dewpoint_f,
+            apparent_temp_f: period.apparent_temp_f,
+            short_desc: Some(period.short_desc.clone()),
+            ..ForecastPoint::new(period.start_time, period.temp_f, period.rain_prob)
+        }
+    }
+}
+
+/// Hours (local time) of the NWS daytime and overnight periods used for daily highs and lows
+const DAY_START_HOUR: u32 = 7;
+const NIGHT_START_HOUR: u32 = 19;
+const NIGHT_END_HOUR: u32 = 8;
+
+/// The forecast high and low for a single day. Either one is None if the forecast doesn't cover
+/// its period.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DailyTemps {
+    /// Highest temperature from 7am to 7pm
+    pub high: Option<i32>,
+    /// Lowest temperature from 7pm the night before to 8am
+    pub low: Option<i32>,
+}
+
+/// Formats a temperature from `DailyTemps`, using "--" if it's missing
+pub fn format_daily_temp(temp: Option<i32>) -> String {
+    temp.map(|t| t.to_string()).unwrap_or_else(|| "--".into())
+}
+
+/// The total forecast precipitation for a single day
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DailyPrecip {
+    /// inches of liquid precipitation, including melted snow
+    pub precip_in: f32,
+    /// inches of snow
+    pub snowfall_in: f32,
+}
+
+/// Formats a precipitation amount in inches, e.g. `0.42"`
+pub fn format_precip_amount(amount_in: f32) -> String {
+    format!("{amount_in:.2}\"")
+}
+
+/// Length of the windows that the forecast is split into when smoothing
+const SMOOTHING_WINDOW_HOURS: i64 = 3;
+
+/// How to smooth the hourly forecast before drawing it on the graph
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Smoothing {
+    /// The temperature from the middle of each 3 hour window, with the rain probability averaged
+    /// over the window
+    #[default]
+    Center,
+    /// The temperature and rain probability both averaged over each 3 hour window
+    Mean,
+    /// The temperature from the middle of each 3 hour window, with the highest rain probability in
+    /// the window so that short showers don't get averaged away
+    MaxRain,
+    /// Every hour is kept, with the temperature and rain probability averaged over the 3 hours
+    /// around it
+    MovingAverage,
+}
+
+/// Settings for how much of the forecast to show and how to smooth it
+#[derive(Debug, Clone)]
+pub struct ForecastOptions {
+    /// Number of days to show, from 1 to 7
+    pub days: u32,
+    pub smoothing: Smoothing,
+}
+
+impl Default for ForecastOptions {
+    fn default() -> ForecastOptions {
+        ForecastOptions {
+            days: 5,
+            smoothing: Smoothing::Center,
+        }
+    }
+}
+
+impl ForecastOptions {
+    /// Reads the `forecast` section of the env data
+    pub fn from_json(data: &Value) -> ForecastOptions {
+        let default = ForecastOptions::default();
+        let days = data["days"].as_u64().map(|d| d as u32).unwrap_or(default.days);
+        if !(1..=7).contains(&days) {
+            panic!("forecast days must be between 1 and 7, got {days}");
+        }
+
+        let smoothing = match data["smoothing"].as_str() {
+            None => default.smoothing,
+            Some("center") => Smoothing::Center,
+            Some("mean") => Smoothing::Mean,
+            Some("max_rain") => Smoothing::MaxRain,
+            Some("moving_average") => Smoothing::MovingAverage,
+            Some(smoothing) => panic!("unknown forecast smoothing {smoothing}, expected center, mean, max_rain or moving_average"),
+        };
+
+        ForecastOptions {
+            days,
+            smoothing,
+        }
+    }
+}
 
 #[derive(Clone)]
-/// Forecast data for the next 5 days
-pub struct Forecast5Day {
+/// Forecast data for the next few days
+pub struct ForecastWindow {
     pub full_forecast: FullForecast,
+    pub smoothing: Smoothing,
 }
 
-impl Forecast5Day {
-    pub fn new(hourly_forecast: &[ForecastPeriod]) -> Forecast5Day {
-        let mut full_forecast: FullForecast = Vec::new();
+impl ForecastWindow {
+    pub fn new(hourly_forecast: &[ForecastPeriod], options: &ForecastOptions) -> ForecastWindow {
+        let points = hourly_forecast.iter().map(ForecastPoint::from).collect();
+        ForecastWindow::from_points(points, options)
+    }
 
-        let start_dt = hourly_forecast[0].start_time;
-        let five_days = Duration::days(5);
-        for s in hourly_forecast {
-            if s.start_time - start_dt > five_days {
-                break;
-            }
-            full_forecast.push((s.start_time, s.temp_f, s.rain_prob));
-        }
+    /// Keeps the points within `options.days` of the first point. Points are sorted by time
+    /// first, so they don't need to be in order or evenly spaced.
+    pub fn from_points(mut points: Vec<ForecastPoint>, options: &ForecastOptions) -> ForecastWindow {
+        points.sort_by_key(|p| p.time);
 
-        // make sure forecast length is divisible by 3 for filtering later
-        let rem = full_forecast.len() % CHUNK_SIZE;
-        for _ in 0..rem { full_forecast.pop(); }
+        let window = Duration::days(options.days as i64);
+        let full_forecast = match points.first() {
+            Some(first) => {
+                let start_dt = first.time;
+                points.into_iter().take_while(|p| p.time - start_dt < window).collect()
+            }
+            None => Vec::new(),
+        };
 
-        Forecast5Day {
+        ForecastWindow {
             full_forecast,
+            smoothing: options.smoothing,
         }
     }
 
-    /// Returns weekly and daily temperatures as (min, max, dailyminmax)
-    /// Keys are simply the day of the month which will not overlap as long as the forecast is 5
-    /// days (i.e. less than a full month)
-    pub fn daily_minmax_temps(&self) -> HashMap<u32, (i32, i32)> {
-        let mut day_min: i32 = 200;
-        let mut day_max: i32 = -100;
-        let mut daily_minmax: HashMap<u32, (i32, i32)> = HashMap::new();
+    /// The nominal time between points in `filtered_forecast`, used to space them out on the
+    /// graph
+    pub fn filtered_step(&self) -> Duration {
+        match self.smoothing {
+            Smoothing::MovingAverage => Duration::hours(1),
+            _ => Duration::hours(SMOOTHING_WINDOW_HOURS),
+        }
+    }
 
-        let mut current_day: u32 = self.full_forecast[0].0.day();
-        for (d, t, _) in &self.full_forecast {
-            let day = d.day();
-            let t = *t;
+    /// Returns the forecast high and low for each date in the forecast, following the NWS
+    /// convention of the high being from 7am to 7pm and the low being from 7pm the night before
+    /// to 8am.
+    ///
+    /// Periods that are already underway when the forecast starts use the rest of the period.
+    /// Periods that the forecast ends partway through are left out, since the rest of the period
+    /// could be warmer or colder.
+    pub fn daily_minmax_temps(&self) -> HashMap<NaiveDate, DailyTemps> {
+        let mut daily_minmax = HashMap::new();
+        let (first, last) = match (self.full_forecast.first(), self.full_forecast.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return daily_minmax,
+        };
+        let last_hour = last.time.naive_local();
 
-            if day != current_day {
-                daily_minmax.insert(current_day, (day_min, day_max));
-                day_min = 200;
-                day_max = -100;
-                current_day = day;
-            }
-            if t < day_min && d.hour() > 12 {
-                day_min = t;
-            }
-            if t > day_max {
-                day_max = t;
-            }
+        for date in first.time.date_naive().iter_days().take_while(|d| *d <= last.time.date_naive()) {
+            let is_daytime = |p: &&ForecastPoint| {
+                p.time.date_naive() == date
+                    && (DAY_START_HOUR..NIGHT_START_HOUR).contains(&p.time.hour())
+            };
+            let is_overnight = |p: &&ForecastPoint| {
+                (p.time.date_naive() == date - Duration::days(1) && p.time.hour() >= NIGHT_START_HOUR)
+                    || (p.time.date_naive() == date && p.time.hour() < NIGHT_END_HOUR)
+            };
+
+            // the hour starting at the end of the period minus one is the last one in it
+            let day_covered = last_hour >= date.and_hms_opt(NIGHT_START_HOUR - 1, 0, 0).unwrap();
+            let night_covered = last_hour >= date.and_hms_opt(NIGHT_END_HOUR - 1, 0, 0).unwrap();
+
+            let high = day_covered
+                .then(|| self.full_forecast.iter().filter(is_daytime).map(|p| p.temp_f).max())
+                .flatten();
+            let low = night_covered
+                .then(|| self.full_forecast.iter().filter(is_overnight).map(|p| p.temp_f).min())
+                .flatten();
+
+            daily_minmax.insert(date, DailyTemps { high, low });
         }
-        // below is commented to ignore update for the last day
-        // if day != current_day {
-        //     daily_minmax.insert(current_day, (day_min, day_max));
-        // }
- 
+
         daily_minmax
     }
 
+    /// Returns the high and low for the next day and night that haven't ended yet at `now`. The
+    /// high is today's until 7pm and then tomorrow's, and the low is from the night ending this
+    /// morning until 8am and then from tonight.
+    pub fn upcoming_temps(&self, now: NaiveDateTime) -> DailyTemps {
+        let daily_minmax = self.daily_minmax_temps();
+        let date = now.date();
+        let tomorrow = date + Duration::days(1);
+        // the lows are keyed by the date the night ends on
+        let high_date = if now.hour() < NIGHT_START_HOUR { date } else { tomorrow };
+        let low_date = if now.hour() < NIGHT_END_HOUR { date } else { tomorrow };
+
+        DailyTemps {
+            high: daily_minmax.get(&high_date).and_then(|t| t.high),
+            low: daily_minmax.get(&low_date).and_then(|t| t.low),
+        }
+    }
+
     /// Returns min and max temps for the week
     pub fn week_minmax_temps(&self) -> (i32, i32) {
         let min_temp = self.full_forecast.iter()
-            .min_by_key(|(_, t, _)| t).expect("no values in forecast").1;
+            .map(|p| p.temp_f).min().expect("no values in forecast");
         let max_temp = self.full_forecast.iter()
-            .max_by_key(|(_, t, _)| t).expect("no values in forecast").1;
+            .map(|p| p.temp_f).max().expect("no values in forecast");
         (min_temp, max_temp)
     }
 
+    /// Returns the total precipitation and snowfall for each day. Days without any amounts in the
+    /// forecast, e.g. because the source doesn't have them, are left out.
+    pub fn daily_precip_totals(&self) -> HashMap<NaiveDate, DailyPrecip> {
+        let mut totals: HashMap<NaiveDate, DailyPrecip> = HashMap::new();
+        for p in &self.full_forecast {
+            if p.precip_in.is_none() && p.snowfall_in.is_none() {
+                continue;
+            }
+            let total = totals.entry(p.time.date_naive()).or_default();
+            total.precip_in += p.precip_in.unwrap_or(0.0);
+            total.snowfall_in += p.snowfall_in.unwrap_or(0.0);
+        }
+        totals
+    }
+
     /// Returns the temperature and rain data filtered to be smoother for drawing on a graph.
+    ///
+    /// The windows are based on the actual times of the points rather than counting points, so
+    /// gaps in the forecast or DST changes don't shift everything after them.
     pub fn filtered_forecast(&self) -> FilteredForecast {
-        let mut forecast = Vec::new();
+        let window = Duration::hours(SMOOTHING_WINDOW_HOURS);
+
+        if self.smoothing == Smoothing::MovingAverage {
+            return self.full_forecast.iter().map(|point| {
+                let nearby: Vec<&ForecastPoint> = self.full_forecast.iter()
+                    .filter(|p| (p.time - point.time).num_seconds().abs() * 2 < window.num_seconds())
+                    .collect();
+                ForecastPoint {
+                    temp_f: average_temp(&nearby),
+                    rain_prob: average(nearby.iter().map(|p| p.rain_prob)).unwrap_or(0),
+                    wind_speed: average(nearby.iter().filter_map(|p| p.wind_speed)),
+                    ..point.clone()
+                }
+            }).collect();
+        }
 
-        for c in self.full_forecast.chunks(CHUNK_SIZE) {
-            let len = c.len();
-            if len != CHUNK_SIZE {
-                panic!("forecast chunk size was not divisible by {CHUNK_SIZE}");
+        // split the forecast up into windows starting from the first point. windows without any
+        // points in them are skipped.
+        let start = match self.full_forecast.first() {
+            Some(first) => first.time,
+            None => return Vec::new(),
+        };
+        let mut chunks: Vec<(i32, Vec<&ForecastPoint>)> = Vec::new();
+        for point in &self.full_forecast {
+            let idx = ((point.time - start).num_seconds() / window.num_seconds()) as i32;
+            match chunks.last_mut() {
+                Some((last_idx, chunk)) if *last_idx == idx => chunk.push(point),
+                _ => chunks.push((idx, vec![point])),
             }
-            let start_time = c[1].0;
-            let temp = c[1].1;
-            let avg_precip = c.iter().map(|t| t.2).sum::<u64>()/len as u64;
+        }
+
+        let mut forecast = Vec::new();
+        for (idx, c) in &chunks {
+            // use the point closest to the middle of the window for the time and anything that
+            // isn't averaged
+            let chunk_middle = start + window * *idx + window / 2;
+            let center = c.iter()
+                .min_by_key(|p| (p.time - chunk_middle).num_seconds().abs())
+                .unwrap();
 
-            forecast.push((start_time, temp, avg_precip));
+            let temp_f = match self.smoothing {
+                Smoothing::Mean => average_temp(c),
+                _ => center.temp_f,
+            };
+            let rain_prob = match self.smoothing {
+                Smoothing::MaxRain => c.iter().map(|p| p.rain_prob).max().unwrap(),
+                _ => average(c.iter().map(|p| p.rain_prob)).unwrap(),
+            };
+            let avg_wind = average(c.iter().filter_map(|p| p.wind_speed));
+            // precipitation amounts add up over the chunk instead of averaging
+            let total = |amount: fn(&ForecastPoint) -> Option<f32>| c.iter().filter_map(|p| amount(p))
+                .fold(None, |total, amount| Some(total.unwrap_or(0.0) + amount));
+            let total_precip = total(|p| p.precip_in);
+            let total_snowfall = total(|p| p.snowfall_in);
+
+            forecast.push(ForecastPoint {
+                temp_f,
+                rain_prob,
+                wind_speed: avg_wind,
+                precip_in: total_precip,
+                snowfall_in: total_snowfall,
+                ..(*center).clone()
+            });
         }
 
         forecast
     }
 }
 
+/// Average temperature of the points, rounded to the nearest degree
+fn average_temp(points: &[&ForecastPoint]) -> i32 {
+    let sum: i32 = points.iter().map(|p| p.temp_f).sum();
+    (sum as f32 / points.len().max(1) as f32).round() as i32
+}
+
+/// Average of the values, or None if there aren't any
+fn average(values: impl Iterator<Item = u64>) -> Option<u64> {
+    let (sum, count) = values.fold((0, 0), |(sum, count), v| (sum + v, count + 1));
+    (count > 0).then(|| sum / count)
+}
+
+/// A temperature observed by the weather station in the past
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+    pub time: DateTime<FixedOffset>,
+    pub temp_f: i32,
+}
+
+/// The latest observation from the weather station. Everything besides the description and
+/// temperature is optional since stations don't always report it.
 #[derive(Debug, Clone)]
 pub struct CurrentWeather {
     pub description: String,
     pub temp_f: i32,
-    // it was coming back as None sometimes and I'm not using it anyway
-    // pub wind_speed: u32,
-    pub rain_in: u32,
+    /// inches of precipitation in the last hour, if the station reported it
+    pub rain_in: Option<f32>,
+    /// mph
+    pub wind_speed: Option<u64>,
+    /// mph
+    pub wind_gust: Option<u64>,
+    /// The direction the wind is coming from in degrees clockwise from north
+    pub wind_direction: Option<f32>,
+    /// percentage out of 100
+    pub humidity: Option<u64>,
+    pub dewpoint_f: Option<i32>,
+    /// inches of mercury
+    pub pressure_inhg: Option<f32>,
+    /// Change in pressure over the last 3 hours in inches of mercury, see `pressure_tendency`
+    pub pressure_tendency_inhg: Option<f32>,
+    /// Only set when it's cold and windy enough to have one, see `wind_chill`
+    pub wind_chill_f: Option<i32>,
+    /// Only set when it's hot enough to have one, see `heat_index`
+    pub heat_index_f: Option<i32>,
+}
+
+impl CurrentWeather {
+    /// Weather with only the description, temperature and rain
+    pub fn new(description: String, temp_f: i32, rain_in: Option<f32>) -> CurrentWeather {
+        CurrentWeather {
+            description,
+            temp_f,
+            rain_in,
+            wind_speed: None,
+            wind_gust: None,
+            wind_direction: None,
+            humidity: None,
+            dewpoint_f: None,
+            pressure_inhg: None,
+            pressure_tendency_inhg: None,
+            wind_chill_f: None,
+            heat_index_f: None,
+        }
+    }
+
+    /// The wind chill or heat index, whichever one there is
+    pub fn feels_like_f(&self) -> Option<i32> {
+        self.wind_chill_f.or(self.heat_index_f)
+    }
+
+    /// A short line with the rest of the conditions, like `feels 21°  wind NW 15 gusts 25  humidity 60%`.
+    /// The feels like temperature is left out if it's close to the actual temperature, and
+    /// anything the station didn't report is left out.
+    pub fn conditions_line(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(feels_like) = self.feels_like_f().filter(|f| (f - self.temp_f).abs() >= 3) {
+            parts.push(format!("feels {feels_like}°"));
+        }
+        match (self.wind_speed, self.wind_direction) {
+            (Some(0), _) => parts.push("calm".to_string()),
+            (Some(speed), direction) => {
+                let mut wind = match direction {
+                    Some(direction) => format!("wind {} {speed}", format_wind_direction(direction)),
+                    None => format!("wind {speed}"),
+                };
+                if let Some(gust) = self.wind_gust.filter(|g| *g > speed) {
+                    wind += &format!(" gusts {gust}");
+                }
+                parts.push(wind);
+            }
+            (None, _) => {}
+        }
+        if let Some(humidity) = self.humidity {
+            parts.push(format!("humidity {humidity}%"));
+        }
+        if let Some(dewpoint_f) = self.dewpoint_f {
+            parts.push(format!("dew pt {dewpoint_f}°"));
+        }
+        if let Some(pressure_inhg) = self.pressure_inhg {
+            parts.push(format!("{pressure_inhg:.2} inHg"));
+        }
+
+        parts.join("  ")
+    }
+}
+
+/// Which way the pressure is going
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PressureTrend {
+    Rising,
+    Steady,
+    Falling,
+}
+
+impl PressureTrend {
+    pub fn from_change(change_inhg: f32) -> PressureTrend {
+        if change_inhg.abs() < PRESSURE_STEADY_INHG {
+            PressureTrend::Steady
+        }
+        else if change_inhg > 0.0 {
+            PressureTrend::Rising
+        }
+        else {
+            PressureTrend::Falling
+        }
+    }
+}
+
+/// The change in pressure over the 3 hours before the `latest` (time, pressure) reading. The
+/// earlier reading is the one from `earlier_readings`, in any order, closest to 3 hours before
+/// the latest one, and there has to be one within half an hour of that.
+pub fn pressure_tendency(latest: (DateTime<FixedOffset>, f32),
+        earlier_readings: &[(DateTime<FixedOffset>, f32)]) -> Option<f32> {
+    let (latest_time, latest_pressure) = latest;
+    let target = latest_time - Duration::hours(PRESSURE_TENDENCY_HOURS);
+    let offset = |time: &DateTime<FixedOffset>| (*time - target).num_seconds().abs();
+    let (_, earlier) = earlier_readings.iter()
+        .filter(|(time, _)| offset(time) <= PRESSURE_TENDENCY_TOLERANCE_MINUTES * 60)
+        .min_by_key(|(time, _)| offset(time))?;
+    Some(latest_pressure - earlier)
+}
+
+/// The NWS wind chill in °F. It's only defined at 50°F and below with winds of at least 3mph.
+pub fn wind_chill(temp_f: f64, wind_mph: f64) -> Option<f64> {
+    if temp_f > 50.0 || wind_mph < 3.0 {
+        return None;
+    }
+    let v = wind_mph.powf(0.16);
+    Some(35.74 + 0.6215*temp_f - 35.75*v + 0.4275*temp_f*v)
+}
+
+/// The NWS heat index in °F, from the Rothfusz regression with its adjustments for very low and
+/// very high humidity. It's only used at 80°F and above, where it starts to be different from the
+/// temperature.
+pub fn heat_index(temp_f: f64, humidity: f64) -> Option<f64> {
+    if temp_f < 80.0 {
+        return None;
+    }
+    let (t, rh) = (temp_f, humidity);
+    // the simple formula is close enough when the result is under 80
+    let simple = 0.5 * (t + 61.0 + (t - 68.0)*1.2 + rh*0.094);
+    if (simple + t) / 2.0 < 80.0 {
+        return Some(simple);
+    }
+
+    let mut index = -42.379 + 2.04901523*t + 10.14333127*rh - 0.22475541*t*rh - 0.00683783*t*t
+        - 0.05481717*rh*rh + 0.00122874*t*t*rh + 0.00085282*t*rh*rh - 0.00000199*t*t*rh*rh;
+    if rh < 13.0 && t <= 112.0 {
+        index -= (13.0 - rh) / 4.0 * ((17.0 - (t - 95.0).abs()) / 17.0).sqrt();
+    }
+    else if rh > 85.0 && t <= 87.0 {
+        index += (rh - 85.0) / 10.0 * (87.0 - t) / 5.0;
+    }
+    Some(index)
+}
+
+/// Shorter versions of common words and phrases in weather.gov descriptions, used when the full
+/// description doesn't fit on the display. Longer phrases come first so they match before their
+/// individual words do.
+const DESCRIPTION_ABBREVIATIONS: [(&str, &str); 22] = [
+    ("Slight Chance", "Sl Chc"),
+    ("in Vicinity", "Nearby"),
+    ("Fog/Mist", "Fog"),
+    ("Thunderstorms", "T-storms"),
+    ("Thunderstorm", "T-storm"),
+    ("Showers", "Shwrs"),
+    ("Chance", "Chc"),
+    ("Likely", "Lkly"),
+    ("Heavy", "Hvy"),
+    ("Light", "Lt"),
+    ("Mostly", "Mstly"),
+    ("Partly", "Ptly"),
+    ("Freezing", "Frz"),
+    ("Precipitation", "Precip"),
+    ("Isolated", "Isol"),
+    ("Scattered", "Sct"),
+    ("Drizzle", "Drzl"),
+    ("Thunder", "Thndr"),
+    ("Pellets", "Plts"),
+    ("Blowing", "Blwg"),
+    ("Overcast", "Ovc"),
+    ("and", "&"),
+];
+
+/// Shortens a weather description using `DESCRIPTION_ABBREVIATIONS`, e.g. "Thunderstorms and Heavy
+/// Rain" becomes "T-storms & Hvy Rain". Only whole words are replaced.
+pub fn abbreviate_description(description: &str) -> String {
+    let mut words: Vec<String> = description.split_whitespace().map(String::from).collect();
+    for (phrase, abbreviation) in DESCRIPTION_ABBREVIATIONS {
+        let phrase: Vec<&str> = phrase.split(' ').collect();
+        let mut i = 0;
+        while i + phrase.len() <= words.len() {
+            let matches = words[i..i+phrase.len()].iter().zip(&phrase)
+                .all(|(w, p)| w.eq_ignore_ascii_case(p));
+            if matches {
+                words.splice(i..i+phrase.len(), [abbreviation.to_string()]);
+            }
+            i += 1;
+        }
+    }
+
+    words.join(" ")
+}
+
+const COMPASS_POINTS: [&str; 16] = ["N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE",
+    "S", "SSW", "SW", "WSW", "W", "WNW", "NW", "NNW"];
+
+/// Converts a 16-point compass direction like "NNW" into degrees clockwise from north
+pub fn parse_wind_direction(direction: &str) -> Option<f32> {
+    COMPASS_POINTS.iter()
+        .position(|p| *p == direction.trim())
+        .map(|i| i as f32 * 22.5)
+}
+
+/// Converts degrees clockwise from north into the nearest 16-point compass direction
+pub fn format_wind_direction(degrees: f32) -> &'static str {
+    let i = (degrees.rem_euclid(360.0) / 22.5).round() as usize;
+    COMPASS_POINTS[i % COMPASS_POINTS.len()]
 }
 
 #[derive(Debug, Clone)]
@@ -128,11 +670,22 @@ pub struct ForecastPeriod {
     /// percentage out of 100
     pub rain_prob: u64,
     pub wind_speed: u64,
+    /// The direction the wind is coming from in degrees clockwise from north
+    pub wind_direction: Option<f32>,
+    pub dewpoint_f: Option<i32>,
     pub short_desc: String,
     pub long_desc: Option<String>,
+    // the rest are only in the gridpoint data, see `merge_gridpoint_data`
+    /// inches of liquid precipitation
+    pub precip_in: Option<f32>,
+    /// inches of snow
+    pub snowfall_in: Option<f32>,
+    /// percentage out of 100
+    pub sky_cover: Option<u64>,
+    pub apparent_temp_f: Option<i32>,
 }
 
-// deprecated in favor of Forecast5Day::filtered_forecast
+// deprecated in favor of ForecastWindow::filtered_forecast
 // /// returns two vecs of (start_hour, temperature, rain_probability)
 // pub(crate) fn gather_5day_forecast(hourly_forecast: &[ForecastPeriod]) -> (FullForecast, AvgForecast) {
 //     let mut avg_out = Vec::new();
@@ -169,18 +722,59 @@ pub fn create_weather_client(env_data: &EnvData) -> Client {
         .build().expect("couldn't create requests client")
 }
 
-pub fn get_current_weather(env_data: &EnvData, client: &Client) -> String {
+/// Gets the station's observations from the last day. The newest one is used for the current
+/// weather and the rest are drawn on the graph.
+pub fn get_observations(env_data: &EnvData, client: &Client) -> String {
     let url = OBSERVATION_DATA_URL.replace("{station}", &env_data.weather_station);
+    let start = Utc::now() - Duration::hours(OBSERVATION_HISTORY_HOURS);
     let json_str = client.get(url)
-        .query(&[("limit", "1")])
+        .query(&[("start", start.to_rfc3339_opts(SecondsFormat::Secs, true))])
         .send()
-        .expect("failed to make current observation request")
+        .expect("failed to make observation request")
         .text()
-        .expect("failed to get text from current observation request");
+        .expect("failed to get text from observation request");
 
     return json_str
 }
 
+/// Returns the observed temperatures from oldest to newest, skipping any observations without a
+/// temperature
+pub fn parse_observation_history(json_str: &str) -> Vec<Observation> {
+    let data: Value = serde_json::from_str(json_str)
+        .expect("failed to parse observation json");
+
+    let mut observations: Vec<Observation> = data["features"].as_array()
+        .expect("observation json did not have features")
+        .iter()
+        .filter_map(|feature| {
+            let data = &feature["properties"];
+            let time = DateTime::parse_from_rfc3339(data["timestamp"].as_str()?)
+                .expect("failed to parse observation timestamp");
+            let temp_f = data["temperature"]["value"].as_f64()?*1.8 + 32.0;
+            Some(Observation { time, temp_f: temp_f as i32 })
+        })
+        .collect();
+    observations.sort_by_key(|o| o.time);
+
+    observations
+}
+
+/// The fields an observation can have the pressure in, in the order they're used. Some stations
+/// only report it at sea level.
+const PRESSURE_FIELDS: [&str; 2] = ["barometricPressure", "seaLevelPressure"];
+
+/// The first of `PRESSURE_FIELDS` that an observation's properties have a value for
+fn observed_pressure_field(data: &Value) -> Option<&'static str> {
+    PRESSURE_FIELDS.into_iter().find(|field| data[*field]["value"].as_f64().is_some())
+}
+
+/// The pressure in `field` of an observation's properties in inches of mercury
+fn observed_pressure_inhg(data: &Value, field: &str) -> Option<f32> {
+    // the pressure is in pascals
+    let pascals = data[field]["value"].as_f64()?;
+    Some((pascals / PA_PER_INHG) as f32)
+}
+
 pub fn parse_current_weather(json_str: &str) -> CurrentWeather {
     let data: Value = serde_json::from_str(json_str)
         .expect("failed to parse current observation json");
@@ -188,83 +782,142 @@ pub fn parse_current_weather(json_str: &str) -> CurrentWeather {
     //println!("current weather");
     //println!("{:#?}", data);
 
-    let data = &data["features"][0]["properties"];
+    // the newest observation is often missing the temperature until it's been processed, so use
+    // the newest one that has it
+    let features = data["features"].as_array().map(|features| features.as_slice()).unwrap_or_default();
+    let current = features.iter()
+        .position(|feature| feature["properties"]["temperature"]["value"].as_f64().is_some())
+        .expect("none of the observations had a temperature");
+
+    // the older observations are only used for how the pressure has changed since the current one.
+    // they're read from the same field as the current one, since the sea level pressure can be
+    // quite different from the station's.
+    let pressure_field = observed_pressure_field(&features[current]["properties"]);
+    let pressure_reading = |feature: &Value| -> Option<(DateTime<FixedOffset>, f32)> {
+        let data = &feature["properties"];
+        let time = DateTime::parse_from_rfc3339(data["timestamp"].as_str()?).ok()?;
+        Some((time, observed_pressure_inhg(data, pressure_field?)?))
+    };
+    let earlier_readings: Vec<(DateTime<FixedOffset>, f32)> = features[current + 1..].iter()
+        .filter_map(pressure_reading)
+        .collect();
+    let pressure_tendency_inhg = pressure_reading(&features[current])
+        .and_then(|latest| pressure_tendency(latest, &earlier_readings));
+
+    let data = &features[current]["properties"];
+
+    let temp_f = data["temperature"]["value"].as_f64().unwrap_or_default()*1.8 + 32.0;
+    let temp_f = temp_f.round() as i32;
 
-    let temp_f = data["temperature"]["value"].as_f64().unwrap()*1.8 + 32.0;
-    let temp_f = temp_f as i32;
-    
-    // convert km to m
-    // let wind_speed = data["windSpeed"]["value"].as_f64().unwrap()*0.621;
-    // let wind_speed = wind_speed as u32;
+    let value = |name: &str| data[name]["value"].as_f64();
+    let to_f = |c: f64| (c*1.8 + 32.0).round() as i32;
 
-    let rain_in = data["precipitationLastHour"]["value"].as_f64().unwrap_or(0.0)*0.039;
-    let rain_in = rain_in as u32;
+    // convert km/h to mph
+    let wind_speed = value("windSpeed").map(|s| (s*MPH_PER_KMH).round() as u64);
+    let wind_gust = value("windGust").map(|s| (s*MPH_PER_KMH).round() as u64);
+    let wind_direction = value("windDirection").map(|d| d as f32);
 
-    let description = data["textDescription"].as_str().unwrap().into();
+    let rain_in = value("precipitationLastHour").map(|r| (r*0.039) as f32);
+
+    let humidity = value("relativeHumidity").map(|h| h.round() as u64);
+    let dewpoint_f = value("dewpoint").map(to_f);
+    let pressure_inhg = pressure_field.and_then(|field| observed_pressure_inhg(data, field));
+
+    // fill in the wind chill and heat index if the station left them out
+    let wind_chill_f = value("windChill").map(to_f)
+        .or_else(|| wind_chill(temp_f as f64, wind_speed? as f64).map(|w| w.round() as i32));
+    let heat_index_f = value("heatIndex").map(to_f)
+        .or_else(|| heat_index(temp_f as f64, humidity? as f64).map(|h| h.round() as i32));
+
+    let description = data["textDescription"].as_str().unwrap_or("").into();
 
     CurrentWeather {
         description,
         temp_f,
-        //wind_speed,
         rain_in,
+        wind_speed,
+        wind_gust,
+        wind_direction,
+        humidity,
+        dewpoint_f,
+        pressure_inhg,
+        pressure_tendency_inhg,
+        wind_chill_f,
+        heat_index_f,
     }
 
 }
 
-pub fn get_daily_forecast(env_data: &EnvData, client: &Client) -> String {
+/// The daily forecast only fills the space under the tasks, so a failed request returns `None`
+/// instead of taking down the display
+pub fn get_daily_forecast(env_data: &EnvData, client: &Client) -> Option<String> {
     let url = DAILY_FORECAST_URL.replace("{office}", &env_data.weather_office)
         .replace("{gridpoint}", &env_data.weather_gridpoint);
-    let json_str = client.get(url).send()
-        .expect("failed to make daily forecast request")
-        .text()
-        .expect("failed to get text from daily forecast request");
-
-    return json_str;
+    client.get(url).send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|response| response.text())
+        .ok()
 }
 
+/// Parses the periods of the daily forecast. Periods that are missing their times or
+/// temperature are skipped, and if the json doesn't parse there aren't any.
 pub fn parse_daily_forecast(json_str: &str) -> Vec<ForecastPeriod> {
-    let data: Value = serde_json::from_str(&json_str)
-        .expect("failed to parse hourly forecast json");
+    let data: Value = match serde_json::from_str(json_str) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("skipping daily forecast that failed to parse: {err}");
+            return Vec::new();
+        }
+    };
 
-    println!("debug data:\n{:#?}", data);
+    let periods = match data["properties"]["periods"].as_array() {
+        Some(periods) => periods,
+        None => {
+            eprintln!("skipping daily forecast without a list of periods");
+            return Vec::new();
+        }
+    };
 
-    let periods = data["properties"]["periods"].as_array()
-        .expect("properties.periods was not an array");
+    periods.iter().filter_map(parse_daily_period).collect()
+}
 
-    let mut output = Vec::new();
-    for period in periods {
-        let period_name = period["name"].as_str().unwrap().parse().ok();
-        let start_time = DateTime::parse_from_rfc3339(&period["startTime"].as_str().unwrap())
-            .expect("failed to parse start time datetime");
-        let end_time = DateTime::parse_from_rfc3339(&period["endTime"].as_str().unwrap())
-            .expect("failed to parse end time datetime");
-        let temp_f = period["temperature"].as_i64().unwrap() as i32;
+fn parse_daily_period(period: &Value) -> Option<ForecastPeriod> {
+    let period_name = period["name"].as_str().map(|name| name.to_string());
+    let start_time = DateTime::parse_from_rfc3339(period["startTime"].as_str()?).ok()?;
+    let end_time = DateTime::parse_from_rfc3339(period["endTime"].as_str()?).ok()?;
+    let temp_f = period["temperature"].as_i64()? as i32;
 
-        let rain_prob = period["probabilityOfPrecipitation"]["value"].as_u64().unwrap_or(0);
+    let rain_prob = period["probabilityOfPrecipitation"]["value"].as_u64().unwrap_or(0);
 
-        let wind_speed = period["windSpeed"].as_str().unwrap().split(' ')
-            .filter_map(|s| s.parse::<u64>().ok())
-            .max()
-            .unwrap_or(0);
+    let wind_speed = period["windSpeed"].as_str().unwrap_or("").split(' ')
+        .filter_map(|s| s.parse::<u64>().ok())
+        .max()
+        .unwrap_or(0);
 
-        let short_desc = period["shortForecast"].as_str().unwrap().to_string();
-        let long_desc_val = period["detailedForecast"].as_str().unwrap().to_string();
-        let long_desc = if long_desc_val.len() == 0 { None } else { Some(long_desc_val) };
+    let wind_direction = period["windDirection"].as_str().and_then(parse_wind_direction);
+    let dewpoint_f = period["dewpoint"]["value"].as_f64().map(|c| (c*1.8 + 32.0).round() as i32);
 
-        let forecast = ForecastPeriod {
-            period_name,
-            start_time,
-            end_time,
-            temp_f,
-            rain_prob,
-            wind_speed,
-            short_desc,
-            long_desc,
-        };
-        output.push(forecast);
-    }
+    let short_desc = period["shortForecast"].as_str().unwrap_or("").to_string();
+    let long_desc = period["detailedForecast"].as_str()
+        .filter(|desc| !desc.is_empty())
+        .map(|desc| desc.to_string());
 
-    output
+    Some(ForecastPeriod {
+        period_name,
+        start_time,
+        end_time,
+        temp_f,
+        rain_prob,
+        wind_speed,
+        wind_direction,
+        dewpoint_f,
+        short_desc,
+        long_desc,
+        precip_in: None,
+        snowfall_in: None,
+        sky_cover: None,
+        apparent_temp_f: None,
+    })
 }
 
 pub fn get_hourly_forecast(env_data: &EnvData, client: &Client) -> String {
@@ -308,6 +961,9 @@ pub fn parse_hourly_forecast(json_str: &str) -> Vec<ForecastPeriod> {
             .max()
             .unwrap_or(0);
 
+        let wind_direction = period["windDirection"].as_str().and_then(parse_wind_direction);
+        let dewpoint_f = period["dewpoint"]["value"].as_f64().map(|c| (c*1.8 + 32.0).round() as i32);
+
         let short_desc = period["shortForecast"].as_str().unwrap().to_string();
         let long_desc = None;
 
@@ -318,11 +974,347 @@ pub fn parse_hourly_forecast(json_str: &str) -> Vec<ForecastPeriod> {
             temp_f,
             rain_prob,
             wind_speed,
+            wind_direction,
+            dewpoint_f,
             short_desc,
             long_desc,
+            precip_in: None,
+            snowfall_in: None,
+            sky_cover: None,
+            apparent_temp_f: None,
         };
         output.push(forecast);
     }
 
     output
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_abbreviate_description() {
+        assert_eq!(abbreviate_description("Thunderstorms and Heavy Rain"), "T-storms & Hvy Rain");
+        assert_eq!(abbreviate_description("Slight Chance Light Snow"), "Sl Chc Lt Snow");
+        assert_eq!(abbreviate_description("Thunderstorm in Vicinity"), "T-storm Nearby");
+        // whole words only
+        assert_eq!(abbreviate_description("Lightning"), "Lightning");
+        assert_eq!(abbreviate_description("Clear"), "Clear");
+    }
+
+    #[test]
+    fn test_parse_wind_direction() {
+        assert_eq!(parse_wind_direction("N"), Some(0.0));
+        assert_eq!(parse_wind_direction("ESE"), Some(112.5));
+        assert_eq!(parse_wind_direction("NW"), Some(315.0));
+        assert_eq!(parse_wind_direction(""), None);
+        assert_eq!(format_wind_direction(112.5), "ESE");
+        assert_eq!(format_wind_direction(350.0), "N");
+        assert_eq!(format_wind_direction(-90.0), "W");
+    }
+
+    #[test]
+    fn test_wind_chill_and_heat_index() {
+        // values from the NWS charts
+        assert_eq!(wind_chill(0.0, 15.0).map(f64::round), Some(-19.0));
+        assert_eq!(wind_chill(30.0, 10.0).map(f64::round), Some(21.0));
+        assert_eq!(wind_chill(55.0, 10.0), None);
+        assert_eq!(wind_chill(30.0, 2.0), None);
+        assert_eq!(heat_index(90.0, 50.0).map(f64::round), Some(95.0));
+        assert_eq!(heat_index(100.0, 40.0).map(f64::round), Some(109.0));
+        assert_eq!(heat_index(75.0, 90.0), None);
+    }
+
+    #[test]
+    fn test_pressure_tendency() {
+        let t = |s: &str| DateTime::parse_from_rfc3339(s).unwrap();
+        let latest = (t("2023-10-16T20:51:00-04:00"), 29.92);
+        let readings = [
+            (t("2023-10-16T18:30:00-04:00"), 30.10),
+            (t("2023-10-16T17:51:00-04:00"), 30.00),
+            (t("2023-10-16T16:51:00-04:00"), 30.20),
+        ];
+        let change = pressure_tendency(latest, &readings).unwrap();
+        assert!((change + 0.08).abs() < 1e-5, "{change}");
+        assert_eq!(PressureTrend::from_change(change), PressureTrend::Falling);
+        assert_eq!(PressureTrend::from_change(0.01), PressureTrend::Steady);
+        assert_eq!(PressureTrend::from_change(0.03), PressureTrend::Rising);
+
+        // nothing from around 3 hours before
+        assert_eq!(pressure_tendency(latest, &readings[..1]), None);
+        assert_eq!(pressure_tendency(latest, &[]), None);
+    }
+
+    #[test]
+    fn test_parse_current_weather() {
+        let json = r#"{"features": [{"properties": {
+            "timestamp": "2023-10-16T20:51:00+00:00",
+            "textDescription": "Cloudy",
+            "temperature": {"value": -5.0},
+            "windSpeed": {"value": 24.0},
+            "windGust": {"value": 40.0},
+            "windDirection": {"value": 310},
+            "relativeHumidity": {"value": 64.6},
+            "dewpoint": {"value": -10.6},
+            "barometricPressure": {"value": 101660},
+            "windChill": {"value": null},
+            "heatIndex": {"value": null},
+            "precipitationLastHour": {"value": null}
+        }}, {"properties": {
+            "timestamp": "2023-10-16T17:51:00+00:00",
+            "textDescription": "Cloudy",
+            "temperature": {"value": -3.0},
+            "barometricPressure": {"value": 101321}
+        }}]}"#;
+        let current = parse_current_weather(json);
+        assert_eq!(current.temp_f, 23);
+        assert_eq!(current.rain_in, None);
+        assert_eq!(current.wind_speed, Some(15));
+        assert_eq!(current.wind_gust, Some(25));
+        assert_eq!(current.humidity, Some(65));
+        assert_eq!(current.dewpoint_f, Some(13));
+        assert_eq!(current.wind_chill_f, Some(10));
+        assert_eq!(current.heat_index_f, None);
+        assert_eq!(current.pressure_tendency_inhg.map(PressureTrend::from_change), Some(PressureTrend::Rising));
+        assert_eq!(current.conditions_line(), "feels 10°  wind NW 15 gusts 25  humidity 65%  dew pt 13°  30.02 inHg");
+
+        // the latest observation is missing the pressure, so the older ones aren't compared
+        let json = r#"{"features": [{"properties": {
+            "timestamp": "2023-10-16T20:51:00+00:00",
+            "textDescription": "Cloudy",
+            "temperature": {"value": -5.0},
+            "barometricPressure": {"value": null}
+        }}, {"properties": {
+            "timestamp": "2023-10-16T19:51:00+00:00",
+            "textDescription": "Cloudy",
+            "temperature": {"value": -4.0},
+            "barometricPressure": {"value": 101660}
+        }}, {"properties": {
+            "timestamp": "2023-10-16T16:51:00+00:00",
+            "textDescription": "Cloudy",
+            "temperature": {"value": -3.0},
+            "barometricPressure": {"value": 101321}
+        }}]}"#;
+        let current = parse_current_weather(json);
+        assert_eq!(current.pressure_inhg, None);
+        assert_eq!(current.pressure_tendency_inhg, None);
+
+        // the observation from 3 hours before only has the sea level pressure, which can't be
+        // compared to the station's
+        let json = r#"{"features": [{"properties": {
+            "timestamp": "2023-10-16T20:51:00+00:00",
+            "textDescription": "Cloudy",
+            "temperature": {"value": -5.0},
+            "barometricPressure": {"value": 98000},
+            "seaLevelPressure": {"value": 101660}
+        }}, {"properties": {
+            "timestamp": "2023-10-16T17:51:00+00:00",
+            "textDescription": "Cloudy",
+            "temperature": {"value": -3.0},
+            "barometricPressure": {"value": null},
+            "seaLevelPressure": {"value": 101660}
+        }}]}"#;
+        let current = parse_current_weather(json);
+        assert!((current.pressure_inhg.unwrap() - 28.94).abs() < 0.01);
+        assert_eq!(current.pressure_tendency_inhg, None);
+
+        // the newest observation doesn't have a temperature yet, so the one before it is used,
+        // along with its pressure for the tendency
+        let json = r#"{"features": [{"properties": {
+            "timestamp": "2023-10-16T21:51:00+00:00",
+            "textDescription": "",
+            "temperature": {"value": null},
+            "barometricPressure": {"value": 101000}
+        }}, {"properties": {
+            "timestamp": "2023-10-16T20:51:00+00:00",
+            "textDescription": "Cloudy",
+            "temperature": {"value": -5.0},
+            "barometricPressure": {"value": 101660}
+        }}, {"properties": {
+            "timestamp": "2023-10-16T17:51:00+00:00",
+            "textDescription": "Cloudy",
+            "temperature": {"value": -3.0},
+            "barometricPressure": {"value": 101321}
+        }}]}"#;
+        let current = parse_current_weather(json);
+        assert_eq!(current.temp_f, 23);
+        assert_eq!(current.description, "Cloudy");
+        assert_eq!(current.pressure_tendency_inhg.map(PressureTrend::from_change), Some(PressureTrend::Rising));
+
+        // a station that only reports the temperature
+        let json = r#"{"features": [{"properties": {"textDescription": "Clear", "temperature": {"value": 20.3}}}]}"#;
+        let current = parse_current_weather(json);
+        // 68.54°F rounds up
+        assert_eq!(current.temp_f, 69);
+        assert_eq!(current.wind_chill_f, None);
+        assert_eq!(current.pressure_tendency_inhg, None);
+        assert_eq!(current.conditions_line(), "");
+    }
+
+    #[test]
+    fn test_parse_daily_forecast() {
+        let json = r#"{"properties": {"periods": [{
+            "name": "Tonight",
+            "startTime": "2023-10-16T18:00:00-04:00",
+            "endTime": "2023-10-17T06:00:00-04:00",
+            "temperature": 51,
+            "windSpeed": "5 to 10 mph",
+            "windDirection": "SW",
+            "shortForecast": "Mostly Cloudy"
+        }, {
+            "name": "Tuesday",
+            "startTime": "2023-10-17T06:00:00-04:00",
+            "endTime": "2023-10-17T18:00:00-04:00",
+            "temperature": null
+        }]}}"#;
+        let periods = parse_daily_forecast(json);
+        // the period without a temperature is skipped
+        assert_eq!(periods.len(), 1);
+        assert_eq!(periods[0].period_name.as_deref(), Some("Tonight"));
+        assert_eq!(periods[0].wind_speed, 10);
+        assert_eq!(periods[0].long_desc, None);
+
+        assert!(parse_daily_forecast("<html>503 Service Unavailable</html>").is_empty());
+        assert!(parse_daily_forecast("{}").is_empty());
+    }
+
+    #[test]
+    fn test_filtered_forecast() {
+        let t = |s: &str| DateTime::parse_from_rfc3339(s).unwrap();
+        // hourly points over the fall DST change, where 01:00 happens twice, followed by a gap
+        // from 03:00 to 05:00
+        let points = vec![
+            ForecastPoint::new(t("2023-11-05T00:00:00-04:00"), 50, 0),
+            ForecastPoint::new(t("2023-11-05T01:00:00-04:00"), 52, 30),
+            ForecastPoint::new(t("2023-11-05T01:00:00-05:00"), 54, 60),
+            ForecastPoint::new(t("2023-11-05T02:00:00-05:00"), 56, 20),
+            ForecastPoint::new(t("2023-11-05T06:00:00-05:00"), 60, 10),
+            ForecastPoint::new(t("2023-11-05T07:00:00-05:00"), 62, 0),
+        ];
+
+        let options = ForecastOptions::default();
+        let forecast = ForecastWindow::from_points(points.clone(), &options).filtered_forecast();
+        let summary: Vec<(i32, u64)> = forecast.iter().map(|p| (p.temp_f, p.rain_prob)).collect();
+        // the second window only has one point in it
+        assert_eq!(summary, vec![(52, 30), (56, 20), (60, 5)]);
+
+        let options = ForecastOptions { smoothing: Smoothing::MaxRain, ..options };
+        let forecast = ForecastWindow::from_points(points.clone(), &options).filtered_forecast();
+        assert_eq!(forecast[0].rain_prob, 60);
+
+        let options = ForecastOptions { smoothing: Smoothing::Mean, ..options };
+        let forecast = ForecastWindow::from_points(points.clone(), &options).filtered_forecast();
+        assert_eq!(forecast[2].temp_f, 61);
+
+        let options = ForecastOptions { days: 1, smoothing: Smoothing::MovingAverage };
+        let forecast = ForecastWindow::from_points(points.clone(), &options).filtered_forecast();
+        assert_eq!(forecast.len(), points.len());
+        assert_eq!(forecast[0].temp_f, 51);
+        // the gap means the 06:00 point only has its neighbor after it
+        assert_eq!(forecast[4].temp_f, 61);
+    }
+
+    #[test]
+    fn test_daily_minmax_temps() {
+        let points = crate::test_data::test_data1();
+        let forecast = ForecastWindow::from_points(points, &ForecastOptions::default());
+        let daily = forecast.daily_minmax_temps();
+        let date = |d: u32| NaiveDate::from_ymd_opt(2023, 10, d).unwrap();
+
+        assert_eq!(daily.len(), 6);
+        assert_eq!(daily[&date(16)], DailyTemps { high: Some(59), low: Some(51) });
+        // the forecast starts at 1am, partway through the first night
+        assert!(daily[&date(12)].low.is_some());
+        // the forecast ends at midnight, so the last night isn't complete
+        assert_eq!(daily[&date(17)], DailyTemps { high: None, low: None });
+        assert_eq!(format_daily_temp(daily[&date(17)].high), "--");
+        assert!(!daily.contains_key(&date(18)));
+
+        let empty = ForecastWindow::from_points(Vec::new(), &ForecastOptions::default());
+        assert!(empty.daily_minmax_temps().is_empty());
+    }
+
+    #[test]
+    fn test_upcoming_temps() {
+        // a live forecast starts at the current hour, so today's periods are only partly in it
+        let forecast_from = |start: &str| {
+            let start = DateTime::parse_from_rfc3339(start).unwrap();
+            let points = (0..72)
+                .map(|h| {
+                    let time = start + Duration::hours(h);
+                    // warmest at 3pm and coldest at 3am
+                    let temp_f = 38 + ((time.hour() as i32 + 9) % 24 - 12).abs();
+                    ForecastPoint::new(time, temp_f, 0)
+                })
+                .collect();
+            ForecastWindow::from_points(points, &ForecastOptions::default())
+        };
+        let now = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().naive_local();
+
+        // during the day it's today's high and tonight's low
+        let forecast = forecast_from("2023-10-16T10:00:00-04:00");
+        assert_eq!(forecast.upcoming_temps(now("2023-10-16T10:30:00-04:00")), DailyTemps { high: Some(50), low: Some(38) });
+
+        // in the evening today's high is over, so it's tomorrow's
+        let forecast = forecast_from("2023-10-16T20:00:00-04:00");
+        assert_eq!(forecast.upcoming_temps(now("2023-10-16T20:30:00-04:00")), DailyTemps { high: Some(50), low: Some(38) });
+
+        // before 8am it's still the night that started yesterday, which is partly in the forecast
+        let forecast = forecast_from("2023-10-16T05:00:00-04:00");
+        assert_eq!(forecast.upcoming_temps(now("2023-10-16T05:30:00-04:00")), DailyTemps { high: Some(50), low: Some(40) });
+    }
+
+    #[test]
+    fn test_precip_type() {
+        let point = |desc: &str, snowfall_in: Option<f32>| ForecastPoint {
+            short_desc: Some(desc.into()),
+            snowfall_in,
+            ..ForecastPoint::new(DateTime::parse_from_rfc3339("2023-10-16T20:00:00-04:00").unwrap(), 30, 80)
+        };
+        assert_eq!(point("Chance Rain Showers", None).precip_type(), Some(PrecipType::Rain));
+        assert_eq!(point("Slight Chance T-storms", None).precip_type(), Some(PrecipType::Rain));
+        assert_eq!(point("Light Snow Likely", None).precip_type(), Some(PrecipType::Snow));
+        assert_eq!(point("Rain And Snow", None).precip_type(), Some(PrecipType::Mixed));
+        assert_eq!(point("Snow And Rain", None).precip_type(), Some(PrecipType::Mixed));
+        assert_eq!(point("Freezing Drizzle", None).precip_type(), Some(PrecipType::Mixed));
+        assert_eq!(point("Patchy Freezing Fog", None).precip_type(), None);
+        assert_eq!(point("Patchy Ice Fog", None).precip_type(), None);
+        assert_eq!(point("Chance Ice Pellets", None).precip_type(), Some(PrecipType::Mixed));
+        assert_eq!(point("Mostly Cloudy", None).precip_type(), None);
+        // the amounts fill in what the description leaves out
+        assert_eq!(point("Mostly Cloudy", Some(0.2)).precip_type(), Some(PrecipType::Snow));
+        assert_eq!(point("Rain Likely", Some(0.2)).precip_type(), Some(PrecipType::Mixed));
+        assert_eq!(ForecastPoint::new(point("", None).time, 30, 0).precip_type(), None);
+    }
+
+    #[test]
+    fn test_daily_precip_totals() {
+        let mut points = crate::test_data::test_data1();
+        // a tenth of an inch an hour for the first 5 hours of the 16th
+        for p in points.iter_mut().filter(|p| p.time.day() == 16 && p.time.hour() < 5) {
+            p.precip_in = Some(0.1);
+        }
+        let forecast = ForecastWindow::from_points(points, &ForecastOptions::default());
+        let totals = forecast.daily_precip_totals();
+
+        assert_eq!(totals.len(), 1);
+        let total = totals[&NaiveDate::from_ymd_opt(2023, 10, 16).unwrap()];
+        assert!((total.precip_in - 0.5).abs() < 1e-6);
+        assert_eq!(total.snowfall_in, 0.0);
+        assert_eq!(format_precip_amount(total.precip_in), "0.50\"");
+    }
+
+    #[test]
+    fn test_parse_observation_history() {
+        let json = r#"{"features": [
+            {"properties": {"timestamp": "2023-10-16T20:51:00+00:00", "temperature": {"value": 20.0}}},
+            {"properties": {"timestamp": "2023-10-16T19:51:00+00:00", "temperature": {"value": null}}},
+            {"properties": {"timestamp": "2023-10-16T18:51:00+00:00", "temperature": {"value": 10.0}}}
+        ]}"#;
+        let observations = parse_observation_history(json);
+        let temps: Vec<i32> = observations.iter().map(|o| o.temp_f).collect();
+        assert_eq!(temps, vec![50, 68]);
+        assert!(observations[0].time < observations[1].time);
+    }
+}